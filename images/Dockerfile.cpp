@@ -1 +1,3 @@
-FROM gcc:latest
\ No newline at end of file
+FROM gcc:latest
+
+RUN apt-get update -y && apt-get install -y time clang-format
\ No newline at end of file
@@ -4,4 +4,4 @@ ENV DEBIAN_FRONTEND="noninteractive"
 
 ENV TZ=Europe/Paris
 
-RUN apt-get update -y && apt-get install -y golang-go
\ No newline at end of file
+RUN apt-get update -y && apt-get install -y golang-go time
\ No newline at end of file
@@ -1 +1,3 @@
 FROM openjdk:latest
+
+RUN microdnf install -y time
\ No newline at end of file
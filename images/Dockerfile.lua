@@ -1,5 +1,5 @@
 FROM ubuntu:latest
 
-RUN apt-get update -y && apt-get install -y lua5.3 \
+RUN apt-get update -y && apt-get install -y lua5.3 time \
     lua-socket \
     lua-sec \
\ No newline at end of file
@@ -1 +1,3 @@
-FROM php:latest
\ No newline at end of file
+FROM php:latest
+
+RUN apt-get update -y && apt-get install -y time
\ No newline at end of file
@@ -0,0 +1,12 @@
+CREATE TABLE snippet_without_usage (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    user        INTEGER NOT NULL,
+    code        TEXT NOT NULL,
+    language    TEXT NOT NULL,
+    guild       TEXT,
+    run_time    TEXT NOT NULL,
+    FOREIGN KEY (user) REFERENCES user (id)
+);
+INSERT INTO snippet_without_usage SELECT id, user, code, language, guild, run_time FROM snippet;
+DROP TABLE snippet;
+ALTER TABLE snippet_without_usage RENAME TO snippet;
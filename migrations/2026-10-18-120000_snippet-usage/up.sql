@@ -0,0 +1,3 @@
+ALTER TABLE snippet ADD COLUMN peak_memory BIGINT;
+ALTER TABLE snippet ADD COLUMN user_time INTEGER;
+ALTER TABLE snippet ADD COLUMN system_time INTEGER;
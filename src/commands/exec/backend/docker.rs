@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::io::{Error, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use duct::cmd;
+
+use crate::commands::exec::backend::{BoundedBuffer, ExecutionBackend, Step};
+use crate::commands::exec::{ExecResult, ResourceUsage, Termination};
+use crate::Settings;
+
+/// Runs a command under GNU time when the image has it, and saves the resources the command
+/// used to `/tmp/.usage`. Images without it run the command as it is.
+///
+/// GNU time writes its report to a pipe held by the wrapper, which the command does not inherit,
+/// and the wrapper only saves it once the command exited, over anything the command left there.
+const USAGE_WRAPPER: &str = concat!(
+    "if [ ! -x /usr/bin/time ]; then exec \"$@\"; fi; ",
+    "rm -f /tmp/.usage; exec 4>&1; ",
+    "usage=$(/usr/bin/time -f '%M %U %S' -o /dev/fd/3 ",
+    "sh -c 'exec 3>&- 4>&- 5>&- 6>&- 7>&- 8>&- 9>&-; exec \"$@\"' sh \"$@\" 3>&1 1>&4 4>&-); ",
+    "status=$?; rm -f /tmp/.usage; printf '%s\\n' \"$usage\" > /tmp/.usage; exit $status"
+);
+
+/// Runs snippets in containers through the `docker` command-line client.
+#[derive(Debug, Default)]
+pub struct DockerBackend {
+    /// Process limit hits of each session when its last failed step was inspected, a session
+    /// starting with none
+    pids_events: Mutex<HashMap<String, u64>>,
+}
+
+impl ExecutionBackend for DockerBackend {
+    fn start_session(&self, settings: &Settings, image: &str) -> Result<String, Error> {
+        let mut args = vec![
+            "run",
+            "--network=none",
+            "--kernel-memory",
+            &settings.kernel_memory,
+            "--cpus",
+            &settings.cpu_load,
+            "--memory",
+            &settings.ram_load,
+        ];
+        let pids_limit = settings.pids_limit.to_string();
+        if settings.pids_limit > 0 {
+            args.extend(&["--pids-limit", &pids_limit]);
+        }
+        args.extend(&["-t", "-d", image]);
+
+        duct::cmd("docker", &args).stdout_capture().read()
+    }
+
+    fn copy_file(&self, session: &str, src: &Path, dest: &Path) -> Result<(), Error> {
+        cmd!(
+            "docker",
+            "cp",
+            src.to_str().unwrap(),
+            format!("{}:{}", session, dest.to_str().unwrap())
+        )
+        .run()
+        .map(|_| ())
+    }
+
+    fn run_step(&self, session: &str, step: &Step) -> Result<ExecResult, Error> {
+        let mut cmd = Command::new("docker");
+        cmd.args(["exec", "-i", "-w", "/home"]);
+        for (key, value) in step.env.iter() {
+            cmd.arg("-e").arg(format!("{}={}", key, value));
+        }
+        cmd.arg(session);
+        if step.measure_usage {
+            cmd.args(["sh", "-c", USAGE_WRAPPER, "sh"]);
+        }
+        cmd.args(&step.command);
+
+        // Programs without input get an empty stdin, so reading from it returns EOF instead of blocking
+        let stdin = step.stdin.clone().unwrap_or_default();
+        let mut res = run_command(
+            cmd,
+            stdin.into_bytes(),
+            step.timeout_seconds,
+            step.output_limit,
+            step.output_sender.clone(),
+        )?;
+
+        if res.timed_out {
+            return Ok(res);
+        }
+        let mut report = None;
+        if step.measure_usage {
+            report = cmd!("docker", "exec", session, "sh", "-c", "cat /tmp/.usage; rm -f /tmp/.usage")
+                .stdout_capture()
+                .stderr_capture()
+                .unchecked()
+                .read()
+                .ok();
+            res.usage = report.as_ref().and_then(|report| ResourceUsage::parse(report));
+        }
+        if res.exit_code != Some(0) && !res.is_truncated() {
+            // The counter covers the whole container, so only new hits tell this step hit the limit
+            let pids_events = read_pids_events(session);
+            let previous = self.pids_events.lock().unwrap().insert(session.to_string(), pids_events);
+            let process_limit = pids_events > previous.unwrap_or(0);
+            res.termination = inspect_termination(session, res.exit_code, report.as_deref(), process_limit);
+        }
+
+        Ok(res)
+    }
+
+    fn teardown(&self, session: &str) {
+        self.pids_events.lock().unwrap().remove(session);
+        let _ = cmd!("docker", "kill", session)
+            .stdout_capture()
+            .stderr_capture()
+            .run();
+        let _ = cmd!("docker", "rm", session)
+            .stdout_capture()
+            .stderr_capture()
+            .run();
+    }
+}
+
+/// Finds out why a command of `session` failed.
+/// `process_limit` tells whether the process limit was hit while the command ran.
+fn inspect_termination(
+    session: &str,
+    exit_code: Option<i32>,
+    report: Option<&str>,
+    process_limit: bool,
+) -> Option<Termination> {
+    let signal = get_signal(exit_code, report);
+
+    // The kernel kills a process with SIGKILL when its container runs out of memory
+    if signal == Some(9) {
+        let oom_killed = cmd!("docker", "inspect", "--format", "{{.State.OOMKilled}}", session)
+            .stdout_capture()
+            .stderr_capture()
+            .unchecked()
+            .read();
+        if oom_killed.is_ok_and(|oom_killed| oom_killed.trim() == "true") {
+            return Some(Termination::OutOfMemory);
+        }
+    }
+
+    if process_limit {
+        return Some(Termination::ProcessLimit);
+    }
+
+    signal.map(Termination::Signal)
+}
+
+/// Returns how many times forks failed on the process limit of `session` since it started,
+/// which the pids cgroup of the container counts.
+fn read_pids_events(session: &str) -> u64 {
+    cmd!(
+        "docker",
+        "exec",
+        session,
+        "sh",
+        "-c",
+        "cat /sys/fs/cgroup/pids.events /sys/fs/cgroup/pids/pids.events 2>/dev/null"
+    )
+    .stdout_capture()
+    .stderr_capture()
+    .unchecked()
+    .read()
+    .map(|events| parse_pids_events(&events))
+    .unwrap_or(0)
+}
+
+/// Returns the signal which killed a command. The report of GNU time tells it, otherwise an
+/// exit code above 128 is taken as one, like shells do. The report is empty when the wrapper
+/// running GNU time was killed along with the command.
+pub fn get_signal(exit_code: Option<i32>, report: Option<&str>) -> Option<i32> {
+    report
+        .and_then(parse_signal)
+        .or_else(|| exit_code.filter(|code| *code > 128 && *code <= 128 + 64).map(|code| code - 128))
+}
+
+/// Parses the `Command terminated by signal N` line GNU time writes when a command is killed.
+pub fn parse_signal(report: &str) -> Option<i32> {
+    report.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("Command terminated by signal ")
+            .and_then(|signal| signal.parse().ok())
+    })
+}
+
+/// Returns how many times the process limit was hit, from the contents of `pids.events`.
+pub fn parse_pids_events(events: &str) -> u64 {
+    events
+        .lines()
+        .filter_map(|line| line.strip_prefix("max "))
+        .filter_map(|count| count.trim().parse::<u64>().ok())
+        .sum()
+}
+
+/// What `run_command` waits for while the command runs.
+enum Event {
+    /// The command exited, at the given instant
+    Exited(Instant),
+    /// The command printed more than the output limit
+    OutputExceeded,
+}
+
+fn read_output<R>(
+    mut stream: R,
+    limit: usize,
+    events: Sender<Event>,
+    sender: Option<Sender<Vec<u8>>>,
+) -> JoinHandle<BoundedBuffer>
+where
+    R: Read + Send + 'static,
+{
+    thread::spawn(move || {
+        let mut buffer = BoundedBuffer::new(limit);
+        let mut chunk = [0; 8192];
+        let mut exceeded = false;
+        loop {
+            match stream.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if !buffer.push(&chunk[..n]) {
+                        if !exceeded {
+                            exceeded = true;
+                            let _ = events.send(Event::OutputExceeded);
+                        }
+                    } else if let Some(ref sender) = sender {
+                        let _ = sender.send(chunk[..n].to_vec());
+                    }
+                }
+            }
+        }
+        buffer
+    })
+}
+
+/// Blocks until the process `pid` exits, leaving it a zombie for `Child::wait` to reap.
+fn wait_without_reaping(pid: u32) {
+    loop {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        let res = unsafe { libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, libc::WEXITED | libc::WNOWAIT) };
+        if res == 0 || Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+            return;
+        }
+    }
+}
+
+/// Runs `cmd` to completion, feeding it `stdin` and capturing its output.
+/// The standard output is also forwarded to `output_sender` as it is read.
+pub fn run_command(
+    mut cmd: Command,
+    stdin: Vec<u8>,
+    timeout_seconds: u64,
+    output_limit: usize,
+    output_sender: Option<Sender<Vec<u8>>>,
+) -> Result<ExecResult, Error> {
+    let start = Instant::now();
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut child_stdin = child.stdin.take().unwrap();
+    thread::spawn(move || {
+        // Dropping the handle closes the program's stdin
+        let _ = child_stdin.write_all(&stdin);
+    });
+
+    // Output is read as it is produced, so a program printing in a loop cannot fill up memory
+    let (events, receiver) = mpsc::channel();
+    let stdout = read_output(
+        child.stdout.take().unwrap(),
+        output_limit,
+        events.clone(),
+        output_sender,
+    );
+    let stderr = read_output(child.stderr.take().unwrap(), output_limit, events.clone(), None);
+
+    // The exit is noticed as it happens on another thread, which leaves the command to be
+    // reaped here, so that killing it can never hit another process reusing its pid
+    let pid = child.id();
+    thread::spawn(move || {
+        wait_without_reaping(pid);
+        let _ = events.send(Event::Exited(Instant::now()));
+    });
+
+    let timeout = Duration::from_secs(timeout_seconds);
+    let mut timed_out = false;
+    let end = loop {
+        let event = if timeout_seconds == 0 || timed_out {
+            receiver.recv().map_err(RecvTimeoutError::from)
+        } else {
+            receiver.recv_timeout(timeout.saturating_sub(start.elapsed()))
+        };
+        match event {
+            Ok(Event::Exited(end)) => break end,
+            Ok(Event::OutputExceeded) => child.kill()?,
+            Err(RecvTimeoutError::Timeout) => {
+                child.kill()?;
+                timed_out = true;
+            }
+            // The waiting thread always sends the exit
+            Err(RecvTimeoutError::Disconnected) => break Instant::now(),
+        }
+    };
+    let status = child.wait()?;
+
+    if timed_out {
+        return Ok(ExecResult {
+            exit_code: None,
+            stdout: "".into(),
+            stderr: "".into(),
+            timed_out: true,
+            duration: end - start,
+            ..Default::default()
+        });
+    }
+
+    let mut res = ExecResult {
+        exit_code: status.code(),
+        duration: end - start,
+        ..Default::default()
+    };
+    res.set_output(stdout.join().unwrap(), stderr.join().unwrap());
+
+    Ok(res)
+}
@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::commands::exec::backend::{BoundedBuffer, ExecutionBackend, Step};
+use crate::commands::exec::ExecResult;
+use crate::Settings;
+
+/// State recorded for every session started on a `FakeBackend`.
+#[derive(Debug, Clone, Default)]
+pub struct FakeSession {
+    pub image: String,
+    pub files: HashMap<PathBuf, String>,
+    pub steps: Vec<Step>,
+    pub torn_down: bool,
+}
+
+type Handler = dyn Fn(&FakeSession, &Step) -> ExecResult + Send + Sync;
+
+/// In-memory backend used by the test harness.
+///
+/// Copied files are kept in memory and every step is answered by `handler`. The
+/// `duration` of the returned `ExecResult` is simulated time: when it reaches the
+/// step timeout, the step is reported as timed out, and output past the step's limit
+/// is dropped, like `DockerBackend` does. The resource usage given by the handler is
+/// only kept for steps that measure it.
+pub struct FakeBackend {
+    handler: Box<Handler>,
+    sessions: Mutex<Vec<FakeSession>>,
+}
+
+impl FakeBackend {
+    pub fn new<F>(handler: F) -> Self
+    where
+        F: Fn(&FakeSession, &Step) -> ExecResult + Send + Sync + 'static,
+    {
+        Self {
+            handler: Box::new(handler),
+            sessions: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn sessions(&self) -> Vec<FakeSession> {
+        self.sessions.lock().unwrap().clone()
+    }
+
+    fn with_session<T, F>(&self, session: &str, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&mut FakeSession) -> T,
+    {
+        let mut sessions = self.sessions.lock().unwrap();
+        let index = session
+            .trim_start_matches("fake-")
+            .parse::<usize>()
+            .map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+        match sessions.get_mut(index) {
+            Some(s) if !s.torn_down => Ok(f(s)),
+            _ => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("No such session: {}", session),
+            )),
+        }
+    }
+}
+
+impl ExecutionBackend for FakeBackend {
+    fn start_session(&self, _settings: &Settings, image: &str) -> Result<String, Error> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.push(FakeSession {
+            image: image.into(),
+            ..Default::default()
+        });
+        Ok(format!("fake-{}", sessions.len() - 1))
+    }
+
+    fn copy_file(&self, session: &str, src: &Path, dest: &Path) -> Result<(), Error> {
+        let contents = fs::read_to_string(src)?;
+        self.with_session(session, |s| {
+            s.files.insert(dest.to_path_buf(), contents);
+        })
+    }
+
+    fn run_step(&self, session: &str, step: &Step) -> Result<ExecResult, Error> {
+        let snapshot = self.with_session(session, |s| {
+            // Keeping the sender would leave the output channel open
+            s.steps.push(Step {
+                output_sender: None,
+                ..step.clone()
+            });
+            s.clone()
+        })?;
+
+        let mut res = (self.handler)(&snapshot, step);
+        if !step.measure_usage {
+            res.usage = None;
+        }
+        if step.timeout_seconds != 0 && res.duration.as_secs() >= step.timeout_seconds {
+            return Ok(ExecResult {
+                timed_out: true,
+                duration: res.duration,
+                ..Default::default()
+            });
+        }
+
+        // Handlers may give raw bytes to simulate programs printing invalid UTF-8
+        let raw = |raw: &[u8], text: &str| if raw.is_empty() { text.as_bytes().to_vec() } else { raw.to_vec() };
+        let mut stdout = BoundedBuffer::new(step.output_limit);
+        let mut stderr = BoundedBuffer::new(step.output_limit);
+        let within_limit = stdout.push(&raw(&res.stdout_raw, &res.stdout))
+            & stderr.push(&raw(&res.stderr_raw, &res.stderr));
+        res.set_output(stdout, stderr);
+        if let Some(ref sender) = step.output_sender {
+            for line in res.stdout_raw.split_inclusive(|&byte| byte == b'\n') {
+                let _ = sender.send(line.to_vec());
+            }
+        }
+        if !within_limit {
+            // The program is killed when it exceeds the output limit
+            res.exit_code = None;
+        }
+
+        Ok(res)
+    }
+
+    fn teardown(&self, session: &str) {
+        let _ = self.with_session(session, |s| s.torn_down = true);
+    }
+}
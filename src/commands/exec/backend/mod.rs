@@ -0,0 +1,125 @@
+use std::io::Error;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+
+use crate::commands::exec::ExecResult;
+use crate::Settings;
+
+mod docker;
+pub use self::docker::{get_signal, parse_pids_events, parse_signal, run_command, DockerBackend};
+
+mod pool;
+pub use self::pool::{ContainerPool, PoolStatus};
+
+#[cfg(test)]
+mod fake;
+#[cfg(test)]
+pub use self::fake::{FakeBackend, FakeSession};
+
+/// A command to run inside a session.
+#[derive(Debug, Default, Clone)]
+pub struct Step {
+    pub command: Vec<String>,
+    pub stdin: Option<String>,
+    pub env: Vec<(String, String)>,
+    /// The command is killed after this many seconds, 0 means no timeout.
+    pub timeout_seconds: u64,
+    /// The command is killed once it writes more than this many bytes to stdout or
+    /// stderr, 0 means no limit.
+    pub output_limit: usize,
+    /// Receives the standard output as it is produced, the channel is closed when the
+    /// command exits.
+    pub output_sender: Option<Sender<Vec<u8>>>,
+    /// Measure the memory and CPU time used by the command, see `ExecResult::usage`.
+    pub measure_usage: bool,
+}
+
+impl Step {
+    pub fn new(command: &str, timeout_seconds: u64) -> Self {
+        Self {
+            command: command.split(' ').map(String::from).collect(),
+            timeout_seconds,
+            ..Default::default()
+        }
+    }
+}
+
+/// Collects an output stream, keeping at most `limit` bytes (0 means no limit) and
+/// counting the bytes dropped past it.
+#[derive(Debug, Default)]
+pub struct BoundedBuffer {
+    data: Vec<u8>,
+    dropped: usize,
+    limit: usize,
+}
+
+impl BoundedBuffer {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    /// Appends `chunk`, returning `false` once the limit has been exceeded.
+    pub fn push(&mut self, chunk: &[u8]) -> bool {
+        let kept = if self.limit == 0 {
+            chunk.len()
+        } else {
+            chunk.len().min(self.limit - self.data.len())
+        };
+        self.data.extend_from_slice(&chunk[..kept]);
+        self.dropped += chunk.len() - kept;
+
+        self.dropped == 0
+    }
+
+    /// Returns the collected bytes and the number of dropped bytes. A character cut in
+    /// half by the limit is dropped as well.
+    pub fn into_parts(mut self) -> (Vec<u8>, usize) {
+        if self.dropped > 0 {
+            let cut = incomplete_char_len(&self.data);
+            self.data.truncate(self.data.len() - cut);
+            self.dropped += cut;
+        }
+
+        (self.data, self.dropped)
+    }
+}
+
+/// Returns the length of the UTF-8 sequence left incomplete at the end of `data`.
+fn incomplete_char_len(data: &[u8]) -> usize {
+    for i in (data.len().saturating_sub(3)..data.len()).rev() {
+        let byte = data[i];
+        if byte & 0xC0 != 0x80 {
+            // Leading byte, its value tells how long the sequence should be
+            let expected = match byte {
+                0xF0..=0xFF => 4,
+                0xE0..=0xEF => 3,
+                0xC0..=0xDF => 2,
+                _ => 1,
+            };
+            let len = data.len() - i;
+            return if len < expected { len } else { 0 };
+        }
+    }
+
+    0
+}
+
+/// Runs code snippets in isolated sessions.
+///
+/// `run_code` only talks to the outside world through this trait, so the whole
+/// execution pipeline can be driven without a container runtime.
+pub trait ExecutionBackend {
+    /// Starts a new session from `image` and returns its identifier.
+    fn start_session(&self, settings: &Settings, image: &str) -> Result<String, Error>;
+    /// Copies a file from the host into the session.
+    fn copy_file(&self, session: &str, src: &Path, dest: &Path) -> Result<(), Error>;
+    /// Runs a single step in the session's working directory.
+    fn run_step(&self, session: &str, step: &Step) -> Result<ExecResult, Error>;
+    /// Destroys the session. Errors are ignored, this is called on every exit path.
+    fn teardown(&self, session: &str);
+}
+
+pub type BoxedBackend = std::sync::Arc<dyn ExecutionBackend + Sync + Send>;
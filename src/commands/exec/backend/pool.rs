@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::commands::exec::backend::{BoxedBackend, ExecutionBackend, Step};
+use crate::commands::exec::ExecResult;
+use crate::Settings;
+
+/// A snapshot of the pool kept for one image.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PoolStatus {
+    pub size: usize,
+    pub idle: usize,
+    pub starting: usize,
+    pub hits: usize,
+    pub misses: usize,
+}
+
+#[derive(Default)]
+struct Pool {
+    size: usize,
+    /// Settings the sessions are started with, instead of the ones of the `ContainerPool`
+    settings: Option<Settings>,
+    /// The image is being built, sessions are started once it is ready
+    waiting: bool,
+    idle: Vec<String>,
+    starting: usize,
+    hits: usize,
+    misses: usize,
+}
+
+type Pools = Arc<Mutex<HashMap<String, Pool>>>;
+
+/// Keeps pre-started sessions for each image, so executions do not have to wait for a
+/// cold start.
+///
+/// Sessions are never reused: each one is handed out once, destroyed on teardown, and
+/// replaced in the background.
+pub struct ContainerPool {
+    backend: BoxedBackend,
+    settings: Settings,
+    pools: Pools,
+}
+
+impl ContainerPool {
+    /// Creates a pool keeping `sizes[image]` idle sessions of each image. Sessions are
+    /// started with the limits of `settings`.
+    pub fn new(backend: BoxedBackend, settings: Settings, sizes: HashMap<String, usize>) -> Self {
+        let pools = sizes
+            .into_iter()
+            .map(|(image, size)| {
+                (
+                    image,
+                    Pool {
+                        size,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        Self {
+            backend,
+            settings,
+            pools: Arc::new(Mutex::new(pools)),
+        }
+    }
+
+    /// Starts the sessions of `image` with the limits of `settings`, e.g. the ones of its
+    /// language, instead of the limits given to `new`.
+    pub fn set_settings(&self, image: &str, settings: Settings) {
+        if let Some(pool) = self.pools.lock().unwrap().get_mut(image) {
+            pool.settings = Some(settings);
+        }
+    }
+
+    /// Holds back the sessions of `image` until `image_ready` is called, e.g. while it is built.
+    pub fn wait_for_image(&self, image: &str) {
+        if let Some(pool) = self.pools.lock().unwrap().get_mut(image) {
+            pool.waiting = true;
+        }
+    }
+
+    /// Starts the sessions of an image held back by `wait_for_image`.
+    pub fn image_ready(&self, image: &str) {
+        if let Some(pool) = self.pools.lock().unwrap().get_mut(image) {
+            pool.waiting = false;
+        }
+        self.refill(image);
+    }
+
+    /// Returns the backend the pooled sessions are started on.
+    pub fn get_backend(&self) -> BoxedBackend {
+        self.backend.clone()
+    }
+
+    /// Starts sessions in the background until every pool is full.
+    pub fn fill(&self) {
+        let images: Vec<String> = self.pools.lock().unwrap().keys().cloned().collect();
+        for image in images {
+            self.refill(&image);
+        }
+    }
+
+    pub fn status(&self) -> HashMap<String, PoolStatus> {
+        self.pools
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(image, pool)| {
+                (
+                    image.clone(),
+                    PoolStatus {
+                        size: pool.size,
+                        idle: pool.idle.len(),
+                        starting: pool.starting,
+                        hits: pool.hits,
+                        misses: pool.misses,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Destroys every idle session, and retires the pools: sessions still starting are
+    /// destroyed once started, and the ones handed out are not replaced anymore.
+    pub fn drain(&self) {
+        let sessions: Vec<String> = self
+            .pools
+            .lock()
+            .unwrap()
+            .values_mut()
+            .flat_map(|pool| {
+                pool.size = 0;
+                pool.idle.drain(..)
+            })
+            .collect();
+        for session in sessions {
+            self.backend.teardown(&session);
+        }
+    }
+
+    fn refill(&self, image: &str) {
+        let (missing, settings) = {
+            let mut pools = self.pools.lock().unwrap();
+            let pool = match pools.get_mut(image) {
+                Some(pool) if !pool.waiting => pool,
+                _ => return,
+            };
+            let missing = pool.size.saturating_sub(pool.idle.len() + pool.starting);
+            pool.starting += missing;
+            (missing, pool.settings.clone().unwrap_or_else(|| self.settings.clone()))
+        };
+        if missing == 0 {
+            return;
+        }
+
+        let backend = self.backend.clone();
+        let pools = self.pools.clone();
+        let image = image.to_string();
+        std::thread::spawn(move || {
+            for _ in 0..missing {
+                let session = backend.start_session(&settings, &image);
+                let mut pools = pools.lock().unwrap();
+                let pool = pools.get_mut(&image).unwrap();
+                pool.starting -= 1;
+                match session {
+                    Ok(session) if pool.idle.len() >= pool.size => {
+                        drop(pools);
+                        backend.teardown(&session);
+                    }
+                    Ok(session) => pool.idle.push(session),
+                    Err(e) => warn!("Could not start a pooled session for {}: {}", image, e),
+                }
+            }
+        });
+    }
+}
+
+impl ExecutionBackend for ContainerPool {
+    fn start_session(&self, settings: &Settings, image: &str) -> Result<String, Error> {
+        let session = {
+            let mut pools = self.pools.lock().unwrap();
+            match pools.get_mut(image) {
+                Some(pool) => {
+                    let session = pool.idle.pop();
+                    if session.is_some() {
+                        pool.hits += 1;
+                    } else {
+                        pool.misses += 1;
+                    }
+                    session
+                }
+                None => None,
+            }
+        };
+        self.refill(image);
+
+        match session {
+            Some(session) => Ok(session),
+            None => self.backend.start_session(settings, image),
+        }
+    }
+
+    fn copy_file(&self, session: &str, src: &Path, dest: &Path) -> Result<(), Error> {
+        self.backend.copy_file(session, src, dest)
+    }
+
+    fn run_step(&self, session: &str, step: &Step) -> Result<ExecResult, Error> {
+        self.backend.run_step(session, step)
+    }
+
+    fn teardown(&self, session: &str) {
+        self.backend.teardown(session);
+    }
+}
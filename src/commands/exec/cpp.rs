@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::commands::exec::language::Language;
+use crate::commands::exec::language::{join_source_paths, Emit, Language};
 
 #[derive(Debug)]
 pub struct Cpp;
@@ -41,6 +41,78 @@ impl Language for Cpp {
         ))
     }
 
+    fn get_compiler_command_for_files(
+        &self,
+        src_paths: &[PathBuf],
+        exe_path: &PathBuf,
+    ) -> Option<String> {
+        Some(format!(
+            "g++ {} -o {}",
+            join_source_paths(src_paths, &["cpp", "cc", "cxx"]),
+            exe_path.to_str().unwrap()
+        ))
+    }
+
+    fn get_allowed_flags(&self) -> Vec<String> {
+        [
+            "-O0",
+            "-O1",
+            "-O2",
+            "-O3",
+            "-Os",
+            "-Ofast",
+            "-Og",
+            "-g",
+            "-Wall",
+            "-Wextra",
+            "-Werror",
+            "-Wpedantic",
+            "-pedantic",
+            "-std=",
+            "-lm",
+            "-pthread",
+            "-march=native",
+            "-fno-exceptions",
+            "-fno-rtti",
+        ]
+        .iter()
+        .map(|flag| flag.to_string())
+        .collect()
+    }
+
+    fn get_emit_kinds(&self) -> Vec<Emit> {
+        vec![Emit::Asm]
+    }
+
+    fn get_emit_command(
+        &self,
+        src_path: &PathBuf,
+        _emit: Emit,
+        opt_level: Option<&str>,
+    ) -> Option<String> {
+        let opt = opt_level.map(|level| format!(" -O{}", level)).unwrap_or_default();
+        Some(format!(
+            "g++ -S -masm=intel -fno-asynchronous-unwind-tables{} {} -o -",
+            opt,
+            src_path.to_str().unwrap()
+        ))
+    }
+
+    fn get_demangler(&self) -> Option<String> {
+        Some("c++filt".into())
+    }
+
+    fn get_formatter_command(&self) -> Option<String> {
+        Some("clang-format --assume-filename=main.cpp".into())
+    }
+
+    fn get_linter_command(&self, src_path: &PathBuf) -> Option<String> {
+        Some(format!(
+            "g++ -fsyntax-only -Wall -Wextra {}",
+            src_path.to_str().unwrap()
+        ))
+    }
+
     fn check_compiler_or_interpreter(&self) -> String {
         String::from("g++ --version")
     }
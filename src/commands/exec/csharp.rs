@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::commands::exec::language::Language;
+use crate::commands::exec::language::{join_source_paths, Language};
 
 #[derive(Debug)]
 pub struct Csharp;
@@ -43,6 +43,14 @@ public class Program
     }
 
     fn get_compiler_command(&self, src_path: &PathBuf, exe_path: &PathBuf) -> Option<String> {
+        self.get_compiler_command_for_files(std::slice::from_ref(src_path), exe_path)
+    }
+
+    fn get_compiler_command_for_files(
+        &self,
+        src_paths: &[PathBuf],
+        exe_path: &PathBuf,
+    ) -> Option<String> {
         let (compiler, out, target, nologo) = if cfg!(windows) {
             (
                 "csc",
@@ -64,7 +72,7 @@ public class Program
             out,
             target,
             nologo,
-            src_path.to_str().unwrap()
+            join_source_paths(src_paths, &["cs"])
         ))
     }
 
@@ -76,6 +84,22 @@ public class Program
         }
     }
 
+    fn get_allowed_flags(&self) -> Vec<String> {
+        [
+            "-optimize",
+            "-optimize+",
+            "-optimize-",
+            "-unsafe",
+            "-checked",
+            "-warnaserror",
+            "-langversion:",
+            "-define:",
+        ]
+        .iter()
+        .map(|flag| flag.to_string())
+        .collect()
+    }
+
     fn check_compiler_or_interpreter(&self) -> String {
         if cfg!(windows) {
             String::from("csc /version")
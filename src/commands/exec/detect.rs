@@ -0,0 +1,187 @@
+use regex::Regex;
+
+/// How sure `detect_language` is of its guess.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confidence {
+    /// The guess should be confirmed by the author before running their code.
+    Low,
+    High,
+}
+
+/// The language guessed for a code section without a language tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Detection {
+    /// A code of the language known by `LangManager`.
+    pub lang_code: &'static str,
+    pub confidence: Confidence,
+}
+
+/// Interpreters named by shebang lines, and the languages they run.
+const SHEBANGS: &[(&str, &str)] = &[
+    ("python", "python"),
+    ("ts-node", "typescript"),
+    ("deno", "typescript"),
+    ("node", "javascript"),
+    ("ruby", "ruby"),
+    ("php", "php"),
+    ("lua", "lua"),
+    ("julia", "julia"),
+    ("runghc", "haskell"),
+    ("ocaml", "ocaml"),
+    ("swipl", "prolog"),
+    ("bash", "shell"),
+    ("zsh", "shell"),
+    ("sh", "shell"),
+];
+
+/// Patterns hinting at each language, with their weight. Each pattern counts once, however
+/// many times it matches.
+const HINTS: &[(&str, &str, u32)] = &[
+    ("rust", r"\bfn\s+\w+\s*(<[^>]*>)?\s*\(", 3),
+    ("rust", r"\blet\s+mut\b", 3),
+    ("rust", r"\b(println|print|eprintln|format|vec|panic|assert_eq)!\s*[(\[]", 4),
+    ("rust", r"\buse\s+std::", 4),
+    ("rust", r"\bimpl(<[^>]*>)?\s+\w+", 2),
+    ("rust", r"&(mut\s+)?(str|self)\b", 2),
+    ("c", r"#include\s*<(stdio|stdlib|string|math|unistd|stdint|stdbool)\.h>", 4),
+    ("c", r"\b(printf|scanf|malloc|free|puts)\s*\(", 2),
+    ("c", r"\bint\s+main\s*\(", 1),
+    ("cpp", r"#include\s*<(iostream|vector|string|map|unordered_map|algorithm|memory|bits/stdc\+\+\.h)>", 5),
+    ("cpp", r"\bstd::", 3),
+    ("cpp", r"\b(cout|cin|endl)\b", 3),
+    ("cpp", r"\b(template\s*<|namespace\s+\w+|using\s+namespace\b)", 2),
+    ("cpp", r"\bint\s+main\s*\(", 1),
+    ("python", r"(?m)^\s*def\s+\w+\s*\(.*\)\s*(->\s*[\w\[\], ]+)?:\s*$", 5),
+    ("python", r"(?m)^\s*(from\s+[\w.]+\s+)?import\s+[\w.]+(\s+as\s+\w+)?\s*$", 2),
+    ("python", r"(?m)^\s*(if|elif|while|for|with|class|try|except)\b.*:\s*$", 2),
+    ("python", r"\b(elif|None|True|False|self|lambda)\b", 2),
+    ("python", r"\bprint\s*\(", 1),
+    ("python", r"\bf'|\bf\x22", 2),
+    ("javascript", r"\bconsole\.(log|error)\s*\(", 4),
+    ("javascript", r"\b(const|let|var)\s+\w+\s*=", 2),
+    ("javascript", r"\bfunction\s*\w*\s*\(", 2),
+    ("javascript", r"=>", 1),
+    ("javascript", r"\b(require\s*\(|module\.exports)", 3),
+    ("javascript", r"===|!==", 2),
+    ("typescript", r"\bconsole\.(log|error)\s*\(", 3),
+    ("typescript", r"\b(const|let|var)\s+\w+\s*:\s*[\w\[\]<>|]+\s*=", 4),
+    ("typescript", r"\b(interface|type)\s+\w+\s*(<[^>]*>)?\s*(=|\{)", 3),
+    ("typescript", r"\(\s*\w+\??\s*:\s*(number|string|boolean|any|unknown)\b", 4),
+    ("typescript", r"\)\s*:\s*(number|string|boolean|void|Promise<)", 4),
+    ("java", r"\bpublic\s+static\s+void\s+main\b", 5),
+    ("java", r"\bSystem\.out\.print", 6),
+    ("java", r"\b(public|private|protected)\s+(static\s+)?(final\s+)?(class|void|int|String)\b", 2),
+    ("java", r"\bimport\s+java\.", 5),
+    ("csharp", r"\bConsole\.(Write|WriteLine|ReadLine)\s*\(", 6),
+    ("csharp", r"\busing\s+System(\.\w+)*\s*;", 5),
+    ("csharp", r"\bstatic\s+void\s+Main\b", 3),
+    ("csharp", r"\bvar\s+\w+\s*=\s*new\b", 2),
+    ("go", r"\bpackage\s+main\b", 6),
+    ("go", r"\bfmt\.(Print|Sprint|Fprint)", 6),
+    ("go", r"\bfunc\s+(\(\w+\s+\*?\w+\)\s*)?\w+\s*\(", 4),
+    ("go", r":=", 2),
+    ("php", r"<\?php", 10),
+    ("php", r"\$\w+\s*(=|->)", 2),
+    ("php", r"\becho\s+[\x22'$]", 2),
+    ("ruby", r"\bputs\b", 3),
+    ("ruby", r"(?m)^\s*end\s*$", 2),
+    ("ruby", r"(\.each(_with_index)?\s+do\b|\bdo\s*\|\w+)", 4),
+    ("ruby", r"(?m)^\s*(def\s+\w+[?!]?|require\s+'\w+')\s*$", 3),
+    ("ruby", r"#\{", 2),
+    ("lua", r"\blocal\s+\w+\s*=", 4),
+    ("lua", r"(?m)^\s*end\s*$", 1),
+    ("lua", r"\b(then|elseif)\b", 2),
+    ("lua", r"\bfunction\s+[\w.:]+\s*\(", 2),
+    ("lua", r"~=|\.\.\s*[\x22'\w]", 2),
+    ("shell", r"(?m)^\s*echo\s", 3),
+    ("shell", r"(?m)^\s*(fi|done|esac)\s*$", 4),
+    ("shell", r"\$\(|\$\{\w+", 2),
+    ("shell", r"(?m)^\s*\w+=[^=\s]", 1),
+    ("shell", r"\[\[?\s+-\w\s", 3),
+    ("haskell", r"(?m)^main\s*=", 4),
+    ("haskell", r"\b(putStrLn|mapM_|print\s+\$)", 5),
+    ("haskell", r"(?m)^\w+\s*::\s*", 4),
+    ("haskell", r"(?m)^import\s+(qualified\s+)?[A-Z][\w.]*", 3),
+    ("kotlin", r"\bfun\s+main\b", 6),
+    ("kotlin", r"\bfun\s+\w+\s*\(", 3),
+    ("kotlin", r"\bval\s+\w+", 2),
+    ("kotlin", r"\bprintln\s*\(", 1),
+    ("julia", r"\bprintln\s*\(", 1),
+    ("julia", r"(?m)^\s*function\s+\w+\s*\([^)]*\)\s*$", 3),
+    ("julia", r"(?m)^\s*(using|import)\s+[A-Z]\w*\s*$", 3),
+    ("julia", r"\b\d+:\d+\b|\.\+|\.\*", 2),
+    ("ocaml", r"\blet\s+(rec\s+)?\w+[^=]*=.*\bin\b", 4),
+    ("ocaml", r"\b(print_endline|print_string|print_int|Printf\.printf|List\.iter)\b", 5),
+    ("ocaml", r";;", 3),
+    ("ocaml", r"\bmatch\s+.+\s+with\b", 3),
+    ("vbnet", r"(?i)\bModule\s+\w+", 3),
+    ("vbnet", r"\bDim\s+\w+", 4),
+    ("vbnet", r"\bEnd\s+(Sub|Module|If|Function)\b", 5),
+    ("prolog", r":-", 3),
+    ("prolog", r"(?m)^\w+\([^)]*\)\s*\.\s*$", 2),
+    ("prolog", r"\b(writeln|format|nl)\b", 1),
+    ("pony", r"\bactor\s+Main\b", 8),
+    ("pony", r"\benv\.out\.print\b", 8),
+];
+
+lazy_static! {
+    /// `HINTS` with their patterns compiled.
+    static ref HINT_REGEXES: Vec<(&'static str, Regex, u32)> = HINTS
+        .iter()
+        .map(|(lang_code, pattern, weight)| (*lang_code, Regex::new(pattern).unwrap(), *weight))
+        .collect();
+}
+
+/// A guess needs this score to be made at all, and twice as much to be sure of it.
+const MIN_SCORE: u32 = 3;
+
+/// Guesses the language of `code` from its shebang line, or from the syntax hints of each
+/// language. The guess is only sure when one language scores well ahead of the others.
+pub fn detect_language(code: &str) -> Option<Detection> {
+    let code = code.trim_start();
+    if let Some(line) = code.lines().next().filter(|line| line.starts_with("#!")) {
+        // The interpreter may come after `env` and its options, e.g. `#!/usr/bin/env -S deno run`
+        let found = line[2..]
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .rev()
+            .find_map(|part| {
+                SHEBANGS
+                    .iter()
+                    .find(|(name, _)| part.starts_with(name))
+                    .map(|(_, lang_code)| *lang_code)
+            });
+        if let Some(lang_code) = found {
+            return Some(Detection {
+                lang_code,
+                confidence: Confidence::High,
+            });
+        }
+    }
+
+    let mut scores: Vec<(&'static str, u32)> = Vec::new();
+    for (lang_code, regex, weight) in HINT_REGEXES.iter() {
+        if !regex.is_match(code) {
+            continue;
+        }
+        match scores.iter_mut().find(|(code, _)| code == lang_code) {
+            Some((_, score)) => *score += weight,
+            None => scores.push((lang_code, *weight)),
+        }
+    }
+    // Ties are broken by the order of the hints
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+
+    let (lang_code, best) = match scores.first() {
+        Some(&(lang_code, best)) if best >= MIN_SCORE => (lang_code, best),
+        _ => return None,
+    };
+    let second = scores.get(1).map(|(_, score)| *score).unwrap_or(0);
+    // The runner-up must be left well behind, by a third of the best score at least
+    let confidence = if best >= MIN_SCORE * 2 && best * 2 >= second * 3 {
+        Confidence::High
+    } else {
+        Confidence::Low
+    };
+
+    Some(Detection { lang_code, confidence })
+}
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::commands::exec::language::Language;
+use crate::commands::exec::language::{filter_asm, Emit, Language};
 
 #[derive(Debug)]
 pub struct Go;
@@ -22,6 +22,48 @@ impl Language for Go {
         Some(format!("go build -o {}", exe_path.to_str().unwrap()))
     }
 
+    fn get_emit_kinds(&self) -> Vec<Emit> {
+        vec![Emit::Asm]
+    }
+
+    fn get_emit_command(
+        &self,
+        src_path: &PathBuf,
+        _emit: Emit,
+        opt_level: Option<&str>,
+    ) -> Option<String> {
+        // Go has no optimisation levels, they can only be turned off along with inlining.
+        // Commands are split on spaces, while go splits the compiler flags on any whitespace.
+        let opt = match opt_level {
+            Some("0") => "\t-N\t-l",
+            _ => "",
+        };
+        // `go build` finds the standard library, which `go tool compile` needs to be told about
+        Some(format!(
+            "go build -gcflags=-S{} -o /tmp/emit.out {}",
+            opt,
+            src_path.to_str().unwrap()
+        ))
+    }
+
+    fn is_emitted_to_stderr(&self) -> bool {
+        true
+    }
+
+    fn filter_emitted_code(&self, code: &str, _emit: Emit) -> String {
+        use regex::Regex;
+
+        // The listing interleaves a hexdump of the machine code and its relocations, and
+        // `go build` heads it with the name of the package
+        let noise = Regex::new(r"^(\s+(0x[0-9a-f]{4}( [0-9a-f]{2}\b)+|rel \d)|# )").unwrap();
+        let code: Vec<&str> = code.lines().filter(|line| !noise.is_match(line)).collect();
+        filter_asm(&code.join("\n"))
+    }
+
+    fn get_formatter_command(&self) -> Option<String> {
+        Some("gofmt".into())
+    }
+
     fn check_compiler_or_interpreter(&self) -> String {
         String::from("go version")
     }
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::commands::exec::language::Language;
+use crate::commands::exec::language::{join_source_paths, Language};
 
 #[derive(Debug)]
 pub struct Java;
@@ -62,10 +62,32 @@ public class {} {{
         Some(format!("javac {}", src_path.to_str().unwrap()))
     }
 
+    fn get_compiler_command_for_files(
+        &self,
+        src_paths: &[PathBuf],
+        _exe_path: &PathBuf,
+    ) -> Option<String> {
+        Some(format!("javac {}", join_source_paths(src_paths, &["java"])))
+    }
+
     fn get_execution_command(&self, path: &PathBuf) -> String {
         format!("java {}", path.to_str().unwrap())
     }
 
+    fn get_allowed_flags(&self) -> Vec<String> {
+        [
+            "-g",
+            "-nowarn",
+            "-Werror",
+            "-Xlint",
+            "-Xlint:",
+            "-parameters",
+        ]
+        .iter()
+        .map(|flag| flag.to_string())
+        .collect()
+    }
+
     fn check_compiler_or_interpreter(&self) -> String {
         String::from("javac -version")
     }
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::commands::exec::language::Language;
+use crate::commands::exec::language::{read_package_list, Language, PACKAGES_DIRECTORY};
 
 #[derive(Debug)]
 pub struct JavaScript;
@@ -32,6 +32,34 @@ impl Language for JavaScript {
         format!("{} {}", self.get_interpreter(), path.to_str().unwrap())
     }
 
+    fn get_allowed_flags(&self) -> Vec<String> {
+        [
+            "--harmony",
+            "--use-strict",
+            "--stack-size=",
+            "--max-old-space-size=",
+        ]
+        .iter()
+        .map(|flag| flag.to_string())
+        .collect()
+    }
+
+    fn get_packages(&self) -> Vec<(String, String)> {
+        let path = format!("{}/javascript/packages.txt", PACKAGES_DIRECTORY);
+        read_package_list(&path, "@")
+    }
+
+    fn get_formatter_command(&self) -> Option<String> {
+        Some("prettier --stdin-filepath main.js".into())
+    }
+
+    fn get_linter_command(&self, src_path: &PathBuf) -> Option<String> {
+        Some(format!(
+            "eslint --no-eslintrc -c /usr/local/lib/eslintrc.json -f unix {}",
+            src_path.to_str().unwrap()
+        ))
+    }
+
     fn check_compiler_or_interpreter(&self) -> String {
         format!("{} --version", self.get_interpreter())
     }
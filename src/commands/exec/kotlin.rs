@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::commands::exec::language::Language;
+use crate::commands::exec::language::{join_source_paths, Language};
 
 #[derive(Debug)]
 pub struct Kotlin;
@@ -54,12 +54,20 @@ impl Language for Kotlin {
         PathBuf::from(self.get_class_name(src_path))
     }
 
-    fn get_compiler_command(&self, src_path: &PathBuf, _exe_path: &PathBuf) -> Option<String> {
+    fn get_compiler_command(&self, src_path: &PathBuf, exe_path: &PathBuf) -> Option<String> {
+        self.get_compiler_command_for_files(std::slice::from_ref(src_path), exe_path)
+    }
+
+    fn get_compiler_command_for_files(
+        &self,
+        src_paths: &[PathBuf],
+        _exe_path: &PathBuf,
+    ) -> Option<String> {
         Some(format!(
             "{} {} -include-runtime -d {}.jar",
             self.get_compiler(),
-            src_path.to_str().unwrap(),
-            self.get_class_name(src_path)
+            join_source_paths(src_paths, &["kt"]),
+            self.get_class_name(&src_paths[0])
         ))
     }
 
@@ -1,5 +1,57 @@
+use std::fs;
 use std::path::PathBuf;
 
+/// Where the manifests of the packages vendored in the images are kept.
+pub const PACKAGES_DIRECTORY: &str = "images/packages";
+
+/// Code a compiler can produce instead of a program, see `Language::get_emit_command`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Emit {
+    Asm,
+    LlvmIr,
+    Mir,
+}
+
+impl Emit {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "asm" | "assembly" => Some(Emit::Asm),
+            "llvm-ir" | "ir" => Some(Emit::LlvmIr),
+            "mir" => Some(Emit::Mir),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Emit::Asm => "Assembly",
+            Emit::LlvmIr => "LLVM IR",
+            Emit::Mir => "MIR",
+        }
+    }
+
+    /// Extension of the file the emitted code is attached as.
+    pub fn get_extension(&self) -> &'static str {
+        match self {
+            Emit::Asm => "s",
+            Emit::LlvmIr => "ll",
+            Emit::Mir => "mir",
+        }
+    }
+
+    /// Language of the code block showing the emitted code, for syntax highlighting.
+    pub fn get_syntax(&self) -> &'static str {
+        match self {
+            Emit::Asm => "x86asm",
+            Emit::LlvmIr => "llvm",
+            Emit::Mir => "rust",
+        }
+    }
+}
+
+/// Optimisation levels accepted by `--opt`, each language maps them to its compiler's flags.
+pub const OPT_LEVELS: [&str; 6] = ["0", "1", "2", "3", "s", "z"];
+
 pub trait Language {
     fn get_image_name(&self) -> String;
     fn get_lang_name(&self) -> String;
@@ -14,15 +66,179 @@ pub trait Language {
     fn get_compiler_command(&self, _src_path: &PathBuf, _exe_path: &PathBuf) -> Option<String> {
         None
     }
+    /// Compiles a snippet made of several files, the first one being the main file.
+    fn get_compiler_command_for_files(
+        &self,
+        src_paths: &[PathBuf],
+        exe_path: &PathBuf,
+    ) -> Option<String> {
+        self.get_compiler_command(&src_paths[0], exe_path)
+    }
+    /// Compiles a snippet against the packages vendored in the image, when it uses some of
+    /// them and the regular compiler cannot find them.
+    fn get_packages_compiler_command(
+        &self,
+        _code: &str,
+        _src_paths: &[PathBuf],
+        _exe_path: &PathBuf,
+    ) -> Option<String> {
+        None
+    }
     fn get_execution_command(&self, path: &PathBuf) -> String {
         String::from(path.to_str().unwrap())
     }
+    /// Flags users may pass to the compiler, or to the interpreter for interpreted languages.
+    /// An entry ending with `=` or `:` accepts any value after it, e.g. `-std=`.
+    fn get_allowed_flags(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Third-party packages vendored in the image, as (name, version) pairs.
+    fn get_packages(&self) -> Vec<(String, String)> {
+        Vec::new()
+    }
+    /// The kinds of code the compiler can emit instead of a program.
+    fn get_emit_kinds(&self) -> Vec<Emit> {
+        Vec::new()
+    }
+    /// Compiles the main file to `emit`, written to the standard output, at `opt_level` when
+    /// one is given (see `OPT_LEVELS`).
+    fn get_emit_command(
+        &self,
+        _src_path: &PathBuf,
+        _emit: Emit,
+        _opt_level: Option<&str>,
+    ) -> Option<String> {
+        None
+    }
+    /// Whether the emit command writes the emitted code to its standard error instead.
+    fn is_emitted_to_stderr(&self) -> bool {
+        false
+    }
+    /// Removes the noise from emitted code.
+    fn filter_emitted_code(&self, code: &str, emit: Emit) -> String {
+        match emit {
+            Emit::Asm => filter_asm(code),
+            Emit::LlvmIr => filter_llvm_ir(code),
+            Emit::Mir => code.to_string(),
+        }
+    }
+    /// A command reading emitted code on its standard input and demangling its symbols.
+    fn get_demangler(&self) -> Option<String> {
+        None
+    }
+    /// The language's canonical formatter, reading code on its standard input and writing
+    /// it formatted to its standard output.
+    fn get_formatter_command(&self) -> Option<String> {
+        None
+    }
+    /// The language's linter, run on `src_path`. It reports its diagnostics one per line, as
+    /// `path:line:column: severity: message` like compilers do, see `commands::lint`.
+    fn get_linter_command(&self, _src_path: &PathBuf) -> Option<String> {
+        None
+    }
     fn check_compiler_or_interpreter(&self) -> String;
     fn get_logo_url(&self) -> String {
-        let name = self.get_lang_name().to_lowercase();
-        format!(
-            "https://raw.githubusercontent.com/Kryod/rustacean/master/logos/{}.png",
-            name
-        )
+        get_default_logo_url(&self.get_lang_name())
     }
 }
+
+/// The logo of a language in the `logos` directory of the repository.
+pub fn get_default_logo_url(lang_name: &str) -> String {
+    format!(
+        "https://raw.githubusercontent.com/Kryod/rustacean/master/logos/{}.png",
+        lang_name.to_lowercase()
+    )
+}
+
+/// Joins the paths of the translation units of a snippet, leaving out headers and other
+/// files whose extension is not in `exts`. The main file is always kept.
+pub fn join_source_paths(src_paths: &[PathBuf], exts: &[&str]) -> String {
+    src_paths
+        .iter()
+        .enumerate()
+        .filter(|(i, path)| {
+            *i == 0
+                || exts
+                    .iter()
+                    .any(|ext| path.extension() == Some(std::ffi::OsStr::new(ext)))
+        })
+        .map(|(_, path)| path.to_str().unwrap())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Strips assembler directives, comments and unused local labels from assembly.
+pub fn filter_asm(code: &str) -> String {
+    let is_label = |line: &str| line.ends_with(':') && !line.contains(char::is_whitespace);
+    let lines: Vec<&str> = code
+        .lines()
+        .filter(|line| {
+            let line = line.trim();
+            !line.is_empty()
+                && !line.starts_with('#')
+                && !line.starts_with(';')
+                && (!line.starts_with('.') || is_label(line))
+        })
+        .collect();
+
+    // Local labels are only worth keeping when an instruction jumps to them
+    let is_used = |label: &str| {
+        lines
+            .iter()
+            .filter(|line| !is_label(line.trim()))
+            .any(|line| {
+                line.split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == '$'))
+                    .any(|word| word == label)
+            })
+    };
+    lines
+        .iter()
+        .filter(|line| {
+            let line = line.trim();
+            !(is_label(line) && line.starts_with(".L") && !is_used(&line[..line.len() - 1]))
+        })
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+/// Strips metadata, attributes and module information from LLVM IR.
+pub fn filter_llvm_ir(code: &str) -> String {
+    code.lines()
+        .filter(|line| {
+            !line.starts_with('!')
+                && !line.starts_with("attributes #")
+                && !line.starts_with("; ModuleID")
+                && !line.starts_with("source_filename")
+                && !line.starts_with("target ")
+        })
+        .map(|line| format!("{}\n", line))
+        .collect::<String>()
+        .trim_start()
+        .to_string()
+}
+
+/// Reads a manifest listing one `name<separator>version` package per line, such as a pip
+/// requirements file. Empty lines and comments are skipped.
+pub fn read_package_list(path: &str, separator: &str) -> Vec<(String, String)> {
+    let list = match fs::read_to_string(path) {
+        Ok(list) => list,
+        Err(e) => {
+            warn!("Could not read package list {}: {}", path, e);
+            return Vec::new();
+        }
+    };
+    list.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            // Scoped npm packages start with the separator
+            match line[1..].find(separator) {
+                Some(i) => (
+                    line[..=i].to_string(),
+                    line[i + 1 + separator.len()..].to_string(),
+                ),
+                None => (line.to_string(), String::from("*")),
+            }
+        })
+        .collect()
+}
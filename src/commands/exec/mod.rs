@@ -1,23 +1,39 @@
 use rand::Rng;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io::{Error, ErrorKind};
 use std::iter;
 use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
 use std::time::{Duration, Instant};
 
-use duct::{cmd, Expression};
 use rand::distributions::Alphanumeric;
 use serenity::{
-    framework::standard::{macros::command, CommandResult},
-    model::{channel::Message, id::UserId},
+    builder::CreateEmbed,
+    framework::standard::{macros::command, Args, CommandResult, Delimiter},
+    http::AttachmentType,
+    model::{
+        channel::{Attachment, Message, ReactionType},
+        id::{ChannelId, MessageId, UserId},
+    },
     prelude::Context,
 };
 
+use typemap::Key;
+
 use crate::{models, DbPool, LangManager, Settings};
 
+pub mod backend;
+pub mod detect;
 pub mod language;
 
+use self::backend::{BoundedBuffer, ExecutionBackend, Step};
+use self::detect::{detect_language, Confidence, Detection};
+use self::language::{Emit, OPT_LEVELS};
+
 mod rust;
 pub use self::rust::Rust;
 
@@ -27,9 +43,6 @@ pub use self::c::C;
 mod cpp;
 pub use self::cpp::Cpp;
 
-mod php;
-pub use self::php::Php;
-
 mod python;
 pub use self::python::Python;
 
@@ -42,30 +55,15 @@ pub use self::csharp::Csharp;
 mod java;
 pub use self::java::Java;
 
-mod lua;
-pub use self::lua::Lua;
-
-mod ruby;
-pub use self::ruby::Ruby;
-
-mod shell;
-pub use self::shell::Shell;
-
 mod asmx86;
 pub use self::asmx86::Asmx86;
 
 mod asmx64;
 pub use self::asmx64::Asmx64;
 
-mod haskell;
-pub use self::haskell::Haskell;
-
 mod kotlin;
 pub use self::kotlin::Kotlin;
 
-mod julia;
-pub use self::julia::Julia;
-
 mod go;
 pub use self::go::Go;
 
@@ -84,6 +82,9 @@ pub use self::prolog::Prolog;
 mod pony;
 pub use self::pony::Pony;
 
+mod toml_language;
+pub use self::toml_language::{load_toml_languages, TomlLanguage, LANGUAGES_DIRECTORY};
+
 #[derive(Debug, Default)]
 pub struct ExecResult {
     pub exit_code: Option<i32>,
@@ -91,6 +92,542 @@ pub struct ExecResult {
     pub stderr: String,
     pub timed_out: bool,
     pub duration: Duration,
+    /// Bytes written past the output limit, which were not captured
+    pub stdout_dropped: usize,
+    pub stderr_dropped: usize,
+    /// The output as it was written by the program, `stdout` and `stderr` are decoded from it
+    pub stdout_raw: Vec<u8>,
+    pub stderr_raw: Vec<u8>,
+    /// Whether invalid UTF-8 was replaced while decoding the output
+    pub invalid_utf8: bool,
+    /// The command that was run, with the user's flags
+    pub command: String,
+    /// Timings of the additional runs requested with `--bench`
+    pub bench: Option<BenchResult>,
+    /// Resources used by the program, when they could be measured
+    pub usage: Option<ResourceUsage>,
+    /// Why the program stopped abnormally, if it did
+    pub termination: Option<Termination>,
+}
+
+/// An abnormal end of a program, other than a timeout or a non-zero exit code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Termination {
+    /// Killed by a signal, e.g. 11 for a segmentation fault
+    Signal(i32),
+    /// Killed by the kernel because the session exceeded its memory limit
+    OutOfMemory,
+    /// The session reached its process limit, so the program could not start new processes
+    ProcessLimit,
+}
+
+impl Termination {
+    /// Describes the termination for the result embed, e.g. `Killed: out of memory (250m limit)`.
+    pub fn describe(&self, settings: &Settings) -> String {
+        match self {
+            Termination::Signal(signal) => match signal_name(*signal) {
+                Some((name, description)) => format!("Killed by {}: {}", name, description),
+                None => format!("Killed by signal {}", signal),
+            },
+            Termination::OutOfMemory => {
+                format!("Killed: out of memory ({} limit)", settings.ram_load)
+            }
+            Termination::ProcessLimit => {
+                format!("Stopped: too many processes ({} limit)", settings.pids_limit)
+            }
+        }
+    }
+}
+
+/// Returns the name and a description of a Linux signal.
+pub fn signal_name(signal: i32) -> Option<(&'static str, &'static str)> {
+    Some(match signal {
+        1 => ("SIGHUP", "hangup"),
+        2 => ("SIGINT", "interrupted"),
+        3 => ("SIGQUIT", "quit"),
+        4 => ("SIGILL", "illegal instruction"),
+        5 => ("SIGTRAP", "trace or breakpoint trap"),
+        6 => ("SIGABRT", "aborted"),
+        7 => ("SIGBUS", "bus error"),
+        8 => ("SIGFPE", "floating point exception"),
+        9 => ("SIGKILL", "killed"),
+        10 => ("SIGUSR1", "user defined signal 1"),
+        11 => ("SIGSEGV", "segmentation fault"),
+        12 => ("SIGUSR2", "user defined signal 2"),
+        13 => ("SIGPIPE", "broken pipe"),
+        14 => ("SIGALRM", "alarm clock"),
+        15 => ("SIGTERM", "terminated"),
+        24 => ("SIGXCPU", "CPU time limit exceeded"),
+        25 => ("SIGXFSZ", "file size limit exceeded"),
+        31 => ("SIGSYS", "bad system call"),
+        _ => return None,
+    })
+}
+
+/// Resources used by a program, as measured inside its session.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ResourceUsage {
+    /// Peak resident set size, in bytes
+    pub peak_memory: u64,
+    pub user_time: Duration,
+    pub system_time: Duration,
+}
+
+impl ResourceUsage {
+    /// Parses a `%M %U %S` report of GNU time, which is on the last line of `report`.
+    pub fn parse(report: &str) -> Option<Self> {
+        let line = report.lines().rev().find(|line| !line.trim().is_empty())?;
+        let mut fields = line.split_whitespace();
+        let peak_memory = fields.next()?.parse::<u64>().ok()?;
+        let mut seconds = || {
+            fields
+                .next()
+                .and_then(|field| field.parse::<f64>().ok())
+                .filter(|secs| *secs >= 0.0)
+                .map(Duration::from_secs_f64)
+        };
+        let user_time = seconds()?;
+        let system_time = seconds()?;
+
+        Some(Self {
+            peak_memory: peak_memory * 1024,
+            user_time,
+            system_time,
+        })
+    }
+}
+
+/// Formats a number of bytes with a binary unit, e.g. `12.5 MiB`.
+pub fn format_memory(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+impl ExecResult {
+    /// Stores the captured output streams, decoding invalid UTF-8 lossily.
+    pub fn set_output(&mut self, stdout: BoundedBuffer, stderr: BoundedBuffer) {
+        let (stdout, stdout_dropped) = stdout.into_parts();
+        let (stderr, stderr_dropped) = stderr.into_parts();
+        let decode = |bytes: &[u8]| match String::from_utf8_lossy(bytes) {
+            Cow::Borrowed(text) => (text.to_string(), false),
+            Cow::Owned(text) => (text, true),
+        };
+        let (stdout_text, stdout_lossy) = decode(&stdout);
+        let (stderr_text, stderr_lossy) = decode(&stderr);
+
+        self.stdout = stdout_text;
+        self.stderr = stderr_text;
+        self.stdout_raw = stdout;
+        self.stderr_raw = stderr;
+        self.stdout_dropped = stdout_dropped;
+        self.stderr_dropped = stderr_dropped;
+        self.invalid_utf8 = stdout_lossy || stderr_lossy;
+    }
+
+    /// Whether the output limit was exceeded, in which case the program was stopped.
+    pub fn is_truncated(&self) -> bool {
+        self.stdout_dropped > 0 || self.stderr_dropped > 0
+    }
+}
+
+/// Timings of a program run several times in the same session, see `--bench`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BenchResult {
+    /// Wall time of every run, without the overhead of starting a command in the session
+    pub runs: Vec<Duration>,
+    /// Number of runs that were asked for, after capping it to the settings
+    pub requested: usize,
+    /// Why the runs stopped before reaching `requested`
+    pub stopped: Option<String>,
+}
+
+impl BenchResult {
+    pub fn min(&self) -> Duration {
+        self.runs.iter().min().cloned().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.runs.iter().max().cloned().unwrap_or_default()
+    }
+
+    pub fn median(&self) -> Duration {
+        let mut runs = self.runs.clone();
+        runs.sort();
+        match runs.len() {
+            0 => Duration::default(),
+            len if len % 2 == 0 => (runs[len / 2 - 1] + runs[len / 2]) / 2,
+            len => runs[len / 2],
+        }
+    }
+
+    /// Population standard deviation of the runs.
+    pub fn stddev(&self) -> Duration {
+        if self.runs.is_empty() {
+            return Duration::default();
+        }
+        let secs: Vec<f64> = self.runs.iter().map(|run| run.as_secs_f64()).collect();
+        let mean = secs.iter().sum::<f64>() / secs.len() as f64;
+        let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / secs.len() as f64;
+        Duration::from_secs_f64(variance.sqrt())
+    }
+}
+
+/// Number of runs of `~bench` when no `--bench` count is given.
+pub const DEFAULT_BENCH_RUNS: usize = 10;
+
+/// Formats a benchmark timing with a unit suited to its magnitude, e.g. `1.25ms`.
+pub fn format_bench_duration(duration: Duration) -> String {
+    let micros = duration.as_secs_f64() * 1_000_000.0;
+    if micros < 1000.0 {
+        format!("{:.0}\u{b5}s", micros)
+    } else if micros < 1_000_000.0 {
+        format!("{:.2}ms", micros / 1000.0)
+    } else {
+        format!("{:.2}s", micros / 1_000_000.0)
+    }
+}
+
+/// Runs `step` up to `runs` times, stopping when a run fails or the runs took `budget` in total.
+///
+/// Every run is a separate command in the session, so the time it takes to start a command is
+/// measured beforehand with a no-op and subtracted from the timings.
+pub fn run_bench(
+    backend: &dyn ExecutionBackend,
+    session: &str,
+    step: &Step,
+    runs: usize,
+    budget: Duration,
+) -> Result<BenchResult, Error> {
+    let mut elapsed = Duration::default();
+    let mut overhead: Option<Duration> = None;
+    for _ in 0..3 {
+        let res = backend.run_step(session, &Step::new("true", 5))?;
+        elapsed += res.duration;
+        if res.exit_code == Some(0) {
+            overhead = Some(overhead.map_or(res.duration, |o| o.min(res.duration)));
+        }
+    }
+    let overhead = overhead.unwrap_or_default();
+
+    let mut bench = BenchResult {
+        requested: runs,
+        ..Default::default()
+    };
+    let mut step = step.clone();
+    for i in 0..runs {
+        if elapsed >= budget {
+            bench.stopped = Some("the execution timeout was reached".into());
+            break;
+        }
+        let remaining = budget - elapsed;
+        step.timeout_seconds = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+
+        let res = backend.run_step(session, &step)?;
+        elapsed += res.duration;
+        if res.timed_out {
+            bench.stopped = Some("the execution timeout was reached".into());
+            break;
+        }
+        if res.is_truncated() {
+            bench.stopped = Some(format!("run {} exceeded the output limit", i + 1));
+            break;
+        }
+        if res.exit_code != Some(0) {
+            let code = res.exit_code.map_or(String::from("none"), |code| code.to_string());
+            bench.stopped = Some(format!("run {} exited with code {}", i + 1, code));
+            break;
+        }
+        bench.runs.push(res.duration.checked_sub(overhead).unwrap_or_default());
+    }
+
+    Ok(bench)
+}
+
+/// Maximum combined length of the arguments and environment variables given to a snippet.
+pub const MAX_EXEC_OPTIONS_LENGTH: usize = 1000;
+
+/// Per-invocation options forwarded to the execution step.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExecOptions {
+    pub stdin: Option<String>,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    /// Flags for the compiler, or the interpreter, checked against the language's allowlist
+    pub flags: Vec<String>,
+    /// Display the program's output as a hexdump
+    pub hex: bool,
+    /// Run the program this many more times after the first run and report its timings
+    pub bench: Option<usize>,
+    /// Show the code the compiler produces instead of running the program
+    pub emit: Option<Emit>,
+    /// Optimisation level of the emitted code, one of `OPT_LEVELS`
+    pub opt_level: Option<String>,
+    /// Language of the snippet, used instead of the tag of its code section
+    pub lang: Option<String>,
+}
+
+/// Parses the options given on the `exec` command line, e.g. `--arg foo --env KEY=value --flag -O2 --hex --bench 10`,
+/// `--emit asm --opt 2` or `--lang python`. Words which are not options are left out.
+pub fn parse_exec_options(line: &str) -> Result<ExecOptions, Error> {
+    let invalid = |msg: String| Error::new(ErrorKind::InvalidInput, msg);
+    let line = line.replace('\n', " ");
+    let args = Args::new(line.trim(), &[Delimiter::Single(' ')]);
+    let mut tokens = args.raw_quoted().filter(|token| !token.is_empty());
+
+    let mut options = ExecOptions::default();
+    while let Some(token) = tokens.next() {
+        let mut value = || {
+            tokens
+                .next()
+                .map(String::from)
+                .ok_or_else(|| invalid(format!("Missing value for `{}`", token)))
+        };
+        match token {
+            "--arg" => options.args.push(value()?),
+            "--flag" => options.flags.push(value()?),
+            "--hex" => options.hex = true,
+            "--lang" => options.lang = Some(value()?.to_ascii_lowercase()),
+            "--emit" => {
+                let value = value()?;
+                match Emit::parse(&value) {
+                    Some(emit) => options.emit = Some(emit),
+                    None => {
+                        return Err(invalid(format!(
+                            "Unknown output `{}`, expected `asm`, `llvm-ir` or `mir`",
+                            value
+                        )))
+                    }
+                }
+            }
+            "--opt" => {
+                let value = value()?;
+                if !OPT_LEVELS.contains(&value.as_str()) {
+                    return Err(invalid(format!(
+                        "Unknown optimisation level `{}`, expected one of `{}`",
+                        value,
+                        OPT_LEVELS.join("`, `")
+                    )));
+                }
+                options.opt_level = Some(value);
+            }
+            "--bench" => {
+                let value = value()?;
+                match value.parse::<usize>() {
+                    Ok(runs) if runs > 0 => options.bench = Some(runs),
+                    _ => {
+                        return Err(invalid(format!(
+                            "Invalid number of runs `{}`, expected a positive number",
+                            value
+                        )))
+                    }
+                }
+            }
+            "--env" => {
+                let value = value()?;
+                let mut split = value.splitn(2, '=');
+                let key = split.next().unwrap_or("");
+                let valid_key = !key.is_empty()
+                    && !key.starts_with(|c: char| c.is_ascii_digit())
+                    && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                match split.next() {
+                    Some(val) if valid_key => options.env.push((key.into(), val.into())),
+                    _ => {
+                        return Err(invalid(format!(
+                            "Invalid environment variable `{}`, expected `NAME=value`",
+                            value
+                        )))
+                    }
+                }
+            }
+            _ if token.starts_with("--") => return Err(invalid(format!("Unknown option `{}`", token))),
+            // Text written before the code sections, e.g. `~exec this prints hi`, is not an option
+            _ => {}
+        }
+    }
+
+    let length = options.args.iter().map(|arg| arg.len() + 1).sum::<usize>()
+        + options.env.iter().map(|(key, val)| key.len() + val.len() + 2).sum::<usize>()
+        + options.flags.iter().map(|flag| flag.len() + 1).sum::<usize>();
+    if length > MAX_EXEC_OPTIONS_LENGTH {
+        return Err(invalid(format!(
+            "Arguments, environment variables and flags cannot exceed {} characters",
+            MAX_EXEC_OPTIONS_LENGTH
+        )));
+    }
+    if options.opt_level.is_some() && options.emit.is_none() {
+        return Err(invalid("`--opt` can only be used along with `--emit`".into()));
+    }
+    if options.bench.is_some() && options.emit.is_some() {
+        return Err(invalid("`--bench` cannot be used along with `--emit`".into()));
+    }
+
+    Ok(options)
+}
+
+/// Makes sure every flag is allowed by the language, see `Language::get_allowed_flags`.
+pub fn check_flags(lang: &BoxedLang, flags: &[String]) -> Result<(), Error> {
+    let allowed = lang.get_allowed_flags();
+    for flag in flags {
+        let is_allowed = allowed.iter().any(|entry| {
+            if entry.ends_with('=') || entry.ends_with(':') {
+                let value = flag.trim_start_matches(entry);
+                flag.starts_with(entry)
+                    && !value.is_empty()
+                    && value
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "_.,+-=".contains(c))
+            } else {
+                flag == entry
+            }
+        });
+        if !is_allowed {
+            let msg = if allowed.is_empty() {
+                format!("{} does not accept any flags", lang.get_lang_name())
+            } else {
+                format!(
+                    "The flag `{}` is not allowed for {}, allowed flags are: `{}`",
+                    flag,
+                    lang.get_lang_name(),
+                    allowed.join("`, `")
+                )
+            };
+            return Err(Error::new(ErrorKind::InvalidInput, msg));
+        }
+    }
+
+    Ok(())
+}
+
+/// A fenced block of a message, split into its info string and its contents.
+/// The info string may name the file after the language, e.g. ` ```c main.c `.
+#[derive(Debug, PartialEq)]
+pub struct CodeBlock {
+    pub tag: String,
+    pub filename: Option<String>,
+    pub code: String,
+}
+
+impl CodeBlock {
+    pub fn is_stdin(&self) -> bool {
+        let tag = self.tag.to_ascii_lowercase();
+        tag == "stdin" || tag == "input"
+    }
+}
+
+/// A source file of a snippet. Unnamed files are given a random name when saved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub name: Option<String>,
+    pub code: String,
+}
+
+impl From<&str> for SourceFile {
+    fn from(code: &str) -> Self {
+        SourceFile::from(code.to_string())
+    }
+}
+
+impl From<String> for SourceFile {
+    fn from(code: String) -> Self {
+        SourceFile { name: None, code }
+    }
+}
+
+/// Turns the code sections of a message into the source files of a snippet.
+/// When there are several of them, each one needs a distinct, plain file name.
+pub fn get_source_files(blocks: Vec<CodeBlock>) -> Result<Vec<SourceFile>, Error> {
+    let multiple = blocks.len() > 1;
+    let mut files: Vec<SourceFile> = Vec::new();
+    for block in blocks {
+        match block.filename {
+            Some(ref name) => {
+                let valid = !name.starts_with('.')
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-');
+                if !valid {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("`{}` is not a valid file name", name),
+                    ));
+                }
+                if files.iter().any(|file| file.name.as_ref() == Some(name)) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!("The file `{}` is defined twice", name),
+                    ));
+                }
+            }
+            None if multiple => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "Please give each code section a file name, e.g. \\`\\`\\`c main.c",
+                ));
+            }
+            None => {}
+        }
+        files.push(SourceFile {
+            name: block.filename,
+            code: block.code,
+        });
+    }
+
+    Ok(files)
+}
+
+/// Counts the lines of a compiler output that point at each of the given files.
+pub fn count_diagnostics_by_file(output: &str, names: &[String]) -> Vec<(String, usize)> {
+    names
+        .iter()
+        .map(|name| {
+            let re = regex::Regex::new(&format!(r"(^|[\s/]){}[:(]\d", regex::escape(name))).unwrap();
+            let count = output.lines().filter(|line| re.is_match(line)).count();
+            (name.clone(), count)
+        })
+        .collect()
+}
+
+pub fn get_code_blocks(content: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut parts = content.split("```").skip(1);
+    while let Some(part) = parts.next() {
+        // Skip the text found between the end of this block and the start of the next one
+        let _ = parts.next();
+
+        let (info, code) = match part.find('\n') {
+            Some(i) => (&part[..i], &part[i + 1..]),
+            None => (part, ""),
+        };
+        // Blocks without a language tag may start on the line of the fence, e.g. ```print(1)```
+        if !is_info_string(info) {
+            blocks.push(CodeBlock { tag: String::new(), filename: None, code: part.trim_start_matches(' ').to_string() });
+            continue;
+        }
+        let mut info = info.split_whitespace();
+        let tag = info.next().unwrap_or("").to_string();
+        let filename = info.next().map(|name| name.to_string());
+        blocks.push(CodeBlock { tag, filename, code: code.to_string() });
+    }
+
+    blocks
+}
+
+/// Tells whether the first line of a code section is an info string, a language tag that may
+/// be followed by a file name, rather than code.
+fn is_info_string(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    let tag = words.next().unwrap_or("");
+    tag.chars().all(|c| c.is_alphanumeric() || "_+#.-".contains(c)) && words.nth(1).is_none()
 }
 
 fn pre_process_code(mut code: String) -> String {
@@ -100,7 +637,7 @@ fn pre_process_code(mut code: String) -> String {
     code
 }
 
-fn pre_process_output(mut output: String) -> String {
+pub fn pre_process_output(mut output: String) -> String {
     output = output.replace("```", "");
     output = output.replace("@everyone", "@ everyone");
     output = output.replace("@here", "@ here");
@@ -117,25 +654,224 @@ pub fn get_lang(lang_manager: &LangManager, lang_code: &str) -> Result<BoxedLang
             if lang_manager.is_language_available(&(*lang)) {
                 Ok(lang)
             } else {
-                Err(Error::new(
-                    ErrorKind::Other,
-                    "This programming language is currently unavailable.",
-                ))
+                Err(get_unavailable_error(lang_manager, &lang))
             }
         }
         None => {
             let langs = lang_manager.get_languages_list();
-            Err(Error::new(
-                ErrorKind::NotFound,
-                format!(
+            let msg = match suggest_lang(lang_manager, lang_code) {
+                Some(suggestion) => format!(
+                    "Unknown programming language `{}`, did you mean `{}`?\nHere are the languages available: {}",
+                    lang_code, suggestion, langs
+                ),
+                None => format!(
                     "Unknown programming language\nHere are the languages available: {}",
                     langs
                 ),
+            };
+            Err(Error::new(ErrorKind::NotFound, msg))
+        }
+    }
+}
+
+fn get_unavailable_error(lang_manager: &LangManager, lang: &BoxedLang) -> Error {
+    if lang_manager.is_language_building(lang) {
+        Error::other("This programming language is being set up, try again in a few minutes.")
+    } else {
+        Error::other("This programming language is currently unavailable.")
+    }
+}
+
+/// Finds the code of an available language closest to `lang_code`, which is likely a typo of it.
+pub fn suggest_lang(lang_manager: &LangManager, lang_code: &str) -> Option<String> {
+    if lang_manager.get(&lang_code.to_ascii_lowercase()).is_some() {
+        return None;
+    }
+    let codes = lang_manager
+        .get_languages()
+        .iter()
+        .filter(|(_, lang)| lang_manager.is_language_available(lang))
+        .flat_map(|(codes, _)| codes.iter());
+
+    find_closest_code(lang_code, codes)
+}
+
+/// Finds the code among `codes` closest to `lang_code`, if it is close enough to be a typo of it.
+pub fn find_closest_code<'a, I>(lang_code: &str, codes: I) -> Option<String>
+where
+    I: Iterator<Item = &'a String>,
+{
+    let lang_code = lang_code.to_ascii_lowercase();
+    let length = lang_code.chars().count();
+    let max_distance = if length <= 2 { 1 } else { 2 };
+    // Among codes as close to it, the one of the most similar length is chosen, e.g. `cpp` for `c++`
+    let mut candidates: Vec<(usize, usize, String)> = codes
+        .map(|code| {
+            let distance = edit_distance(&lang_code, code);
+            (distance, (code.chars().count() as isize - length as isize).unsigned_abs(), code.clone())
+        })
+        .filter(|(distance, _, code)| *distance > 0 && *distance <= max_distance && *distance < code.len())
+        .collect();
+    candidates.sort();
+
+    candidates.into_iter().next().map(|(_, _, code)| code)
+}
+
+/// Levenshtein distance between two strings, in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + if ca == *cb { 0 } else { 1 };
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Finds the language of a code section from its tag, or guesses it from its code when it
+/// has no tag or an unknown one that is not a typo. The guess is returned along with the language.
+pub fn get_lang_or_detect(
+    lang_manager: &LangManager,
+    tag: &str,
+    code: &str,
+) -> Result<(BoxedLang, Option<Detection>), Error> {
+    if !tag.is_empty() {
+        match get_lang(lang_manager, tag) {
+            Err(ref e) if e.kind() == ErrorKind::NotFound && suggest_lang(lang_manager, tag).is_none() => {}
+            res => return res.map(|lang| (lang, None)),
+        }
+    }
+
+    match detect_language(code) {
+        Some(detection) => get_lang(lang_manager, detection.lang_code).map(|lang| (lang, Some(detection))),
+        None if tag.is_empty() => Err(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "Please add the language of your code after the \\`\\`\\`, I could not guess it\nHere are the languages available: {}",
+                lang_manager.get_languages_list()
+            ),
+        )),
+        None => get_lang(lang_manager, tag).map(|lang| (lang, None)),
+    }
+}
+
+/// Finds the language of a source file from its extension, see `Language::get_source_file_ext`.
+pub fn get_lang_by_filename(lang_manager: &LangManager, filename: &str) -> Result<BoxedLang, Error> {
+    let ext = match filename.rfind('.') {
+        Some(i) => filename[i..].to_ascii_lowercase(),
+        None => {
+            return Err(Error::new(
+                ErrorKind::NotFound,
+                format!("Cannot tell the language of `{}`, it has no extension", filename),
+            ));
+        }
+    };
+    let mut langs: Vec<BoxedLang> = lang_manager
+        .get_languages()
+        .values()
+        .filter(|lang| lang.get_source_file_ext() == ext)
+        .cloned()
+        .collect();
+    langs.sort_by_key(|lang| lang.get_lang_name());
+    langs.dedup_by_key(|lang| lang.get_lang_name());
+
+    match langs.len() {
+        0 => Err(Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "Unknown file extension `{}`\nHere are the languages available: {}",
+                ext,
+                lang_manager.get_languages_list()
+            ),
+        )),
+        1 => {
+            let lang = langs.remove(0);
+            if lang_manager.is_language_available(&lang) {
+                Ok(lang)
+            } else {
+                Err(get_unavailable_error(lang_manager, &lang))
+            }
+        }
+        _ => {
+            let names: Vec<String> = langs.iter().map(|lang| lang.get_lang_name()).collect();
+            Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "`{}` files could be {}, please send the code in a code section tagged with its language",
+                    ext,
+                    names.join(" or ")
+                ),
             ))
         }
     }
 }
 
+/// Downloads the source files uploaded with a message, refusing files over `limit` bytes.
+/// Every file refused is reported along with the reason it was refused for.
+fn download_source_files(attachments: &[Attachment], limit: u64) -> Result<Vec<CodeBlock>, Error> {
+    let mut blocks = Vec::new();
+    let mut rejected = Vec::new();
+    for attachment in attachments {
+        let data = if attachment.size > limit {
+            None
+        } else {
+            Some(attachment.download().map_err(|e| e.to_string()))
+        };
+        match check_source_file(&attachment.filename, data, limit) {
+            Ok(block) => blocks.push(block),
+            Err(reason) => rejected.push((attachment.filename.clone(), reason)),
+        }
+    }
+    if !rejected.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, format_rejected_files(&rejected)));
+    }
+
+    Ok(blocks)
+}
+
+/// Turns an uploaded file into a code section, or tells why it cannot be run. `data` is the
+/// downloaded file, or `None` when it was not downloaded for being too large.
+pub fn check_source_file(
+    filename: &str,
+    data: Option<Result<Vec<u8>, String>>,
+    limit: u64,
+) -> Result<CodeBlock, String> {
+    let too_large = format!("too large, source files are limited to {} bytes", limit);
+    let data = match data {
+        Some(Ok(data)) => data,
+        Some(Err(e)) => return Err(format!("could not be downloaded ({})", e)),
+        None => return Err(too_large),
+    };
+    if data.len() as u64 > limit {
+        return Err(too_large);
+    }
+    let code = String::from_utf8(data).map_err(|_| String::from("not a text file"))?;
+
+    Ok(CodeBlock {
+        tag: String::new(),
+        filename: Some(filename.to_string()),
+        code,
+    })
+}
+
+/// Lists the uploaded files which were refused, with the reason of each.
+pub fn format_rejected_files(rejected: &[(String, String)]) -> String {
+    let lines: Vec<String> = rejected
+        .iter()
+        .map(|(filename, reason)| format!("`{}`: {}", filename, reason))
+        .collect();
+    match lines.len() {
+        1 => format!("Cannot run {}", lines[0]),
+        n => format!("Cannot run {} of the uploaded files:\n{}", n, lines.join("\n")),
+    }
+}
+
 fn append_to_msg(ctx: &Option<&mut Context>, msg: &mut Option<&mut Message>, line: &str) {
     if let Some(ref mut msg) = msg {
         if let Some(ctx) = ctx {
@@ -145,132 +881,221 @@ fn append_to_msg(ctx: &Option<&mut Context>, msg: &mut Option<&mut Message>, lin
     };
 }
 
+/// Maximum number of characters of output shown in the reply while a snippet is running.
+pub const STREAM_VIEW_LENGTH: usize = 1500;
+
+/// Shows the output of a running snippet under the progress lines of the reply.
+pub fn format_stream_view(header: &str, output: &[u8]) -> String {
+    let text = pre_process_output(String::from_utf8_lossy(output).into_owned());
+    let skip = text.chars().count().saturating_sub(STREAM_VIEW_LENGTH);
+    let tail = text.chars().skip(skip).collect::<String>();
+    format!("{}\n```\n{}\n```", header, tail)
+}
+
+/// Edits the reply with the output received so far, at most once per `interval` to stay
+/// within Discord's rate limits. Returns once the channel is closed.
+fn stream_output(ctx: &Context, msg: &mut Message, receiver: Receiver<Vec<u8>>, interval: Duration) {
+    let header = msg.content.clone();
+    let mut output = Vec::new();
+    let mut last_edit = Instant::now();
+    let mut changed = false;
+    loop {
+        match receiver.recv_timeout(interval) {
+            Ok(chunk) => {
+                output.extend(chunk);
+                // Only the end of the output is shown, characters take at most 4 bytes
+                if output.len() > STREAM_VIEW_LENGTH * 8 {
+                    output.drain(..output.len() - STREAM_VIEW_LENGTH * 4);
+                }
+                changed = true;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if changed && last_edit.elapsed() >= interval {
+            let content = format_stream_view(&header, &output);
+            let _ = msg.edit(ctx, |m| m.content(content));
+            last_edit = Instant::now();
+            changed = false;
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn run_code(
     settings: &Settings,
-    mut code: String,
+    backend: &dyn ExecutionBackend,
+    files: Vec<SourceFile>,
     lang: BoxedLang,
+    options: &ExecOptions,
     author: UserId,
     ctx: Option<&mut Context>,
     mut reply: Option<&mut Message>,
 ) -> Result<(ExecResult, ExecResult, String, String), Error> {
+    let settings = &settings.for_language(&lang.get_lang_name());
+    check_flags(&lang, &options.flags)?;
+    if let Some(emit) = options.emit {
+        if !lang.get_emit_kinds().contains(&emit) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} cannot show its {}, try C, C++, Rust or Go",
+                    lang.get_lang_name(),
+                    emit.get_name().to_lowercase()
+                ),
+            ));
+        }
+    }
+    if options.bench.is_some() && settings.bench_max_runs == 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Benchmarks are disabled on this bot.",
+        ));
+    }
+
     append_to_msg(&ctx, &mut reply, "Saving code...");
-    let src_path = match save_code(&code, author, &lang.get_source_file_ext()) {
-        Ok(path) => path,
-        Err(e) => {
+    // Source files, as (path on the host, path in the container)
+    let mut sources: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let remove_sources = |sources: &[(PathBuf, PathBuf)]| {
+        for (src_path, _) in sources {
+            let _ = fs::remove_file(src_path);
+        }
+    };
+    for file in files.iter() {
+        let src_path = match save_code(
+            &pre_process_code(file.code.clone()),
+            author,
+            &lang.get_source_file_ext(),
+        ) {
+            Ok(path) => path,
+            Err(e) => {
+                remove_sources(&sources);
+                return Err(Error::other(format!("An error occurred: {}", e)));
+            }
+        };
+        info!(
+            "Saved {} code in {}.",
+            lang.get_lang_name(),
+            src_path.to_str().unwrap()
+        );
+
+        let name = match file.name {
+            Some(ref name) => name.as_str(),
+            None => src_path.file_name().unwrap().to_str().unwrap(),
+        };
+        let path_in_container = PathBuf::from("/home").join(name);
+        sources.push((src_path, path_in_container));
+    }
+    let (src_path, path_in_container) = match sources.first() {
+        Some(source) => source.clone(),
+        None => {
             return Err(Error::new(
-                ErrorKind::Other,
-                format!("An error occurred: {}", e),
+                ErrorKind::InvalidInput,
+                "There is no code to run.",
             ));
         }
     };
-    info!(
-        "Saved {} code in {}.",
-        lang.get_lang_name(),
-        src_path.to_str().unwrap()
-    );
-
-    code = pre_process_code(code);
-    if let Some(modified) = lang.pre_process_code(&code, &src_path) {
+
+    // Only the main file is wrapped, the other ones are left as they are. Emitted code is
+    // compiled as it is, it does not need a main function
+    let mut code = pre_process_code(files[0].code.clone());
+    let modified = match options.emit {
+        Some(_) => None,
+        None => lang.pre_process_code(&code, &path_in_container),
+    };
+    if let Some(modified) = modified {
         match fs::write(src_path.as_path(), &modified) {
             Ok(_) => {}
             Err(e) => {
-                return Err(Error::new(
-                    ErrorKind::Other,
-                    format!("An error occurred: {}", e),
-                ));
+                remove_sources(&sources);
+                return Err(Error::other(format!("An error occurred: {}", e)));
             }
         };
         code = modified;
     }
 
-    let path_in_container = PathBuf::from("/home").join(src_path.file_name().unwrap());
+    let src_paths: Vec<PathBuf> = sources.iter().map(|(_, path)| path.clone()).collect();
     let image = lang.get_image_name();
     let out_path = lang.get_out_path(&path_in_container);
 
     // Start container
     append_to_msg(&ctx, &mut reply, "Starting session...");
-    let cmd = cmd!(
-        "docker",
-        "run",
-        "--network=none",
-        "--kernel-memory",
-        &settings.kernel_memory,
-        "--cpus",
-        &settings.cpu_load,
-        "--memory",
-        &settings.ram_load,
-        "-t",
-        "-d",
-        image
-    );
-    let container_id = cmd.stdout_capture().read()?;
+    let session = match backend.start_session(settings, &image) {
+        Ok(session) => session,
+        Err(e) => {
+            remove_sources(&sources);
+            return Err(e);
+        }
+    };
     let cleanup = || {
-        let _ = fs::remove_file(&src_path);
-        let _ = cmd!("docker", "kill", &container_id)
-            .stdout_capture()
-            .stderr_capture()
-            .run();
-        let _ = cmd!("docker", "rm", &container_id)
-            .stdout_capture()
-            .stderr_capture()
-            .run();
+        remove_sources(&sources);
+        backend.teardown(&session);
     };
 
-    // Copy source file to container
+    // Copy source files to container
     append_to_msg(&ctx, &mut reply, "Copying code snippet...");
-    let cmd = cmd!(
-        "docker",
-        "cp",
-        src_path.to_str().unwrap(),
-        format!("{}:{}", container_id, path_in_container.to_str().unwrap())
-    );
-    match cmd.run() {
-        Ok(_) => {}
-        Err(e) => {
-            cleanup();
-            return Err(Error::new(
-                ErrorKind::Other,
-                format!("Could not copy code snippet to container: {}", e),
-            ));
-        }
-    };
+    for (src_path, path_in_container) in sources.iter() {
+        match backend.copy_file(&session, src_path, path_in_container) {
+            Ok(_) => {}
+            Err(e) => {
+                cleanup();
+                return Err(Error::other(format!(
+                    "Could not copy code snippet to container: {}",
+                    e
+                )));
+            }
+        };
+    }
 
     // Compile code if necessary
+    let compiler_command = match options.emit {
+        Some(emit) => lang.get_emit_command(&path_in_container, emit, options.opt_level.as_deref()),
+        None => lang
+            .get_packages_compiler_command(&code, &src_paths, &out_path)
+            .or_else(|| lang.get_compiler_command_for_files(&src_paths, &out_path)),
+    };
     let compilation: Result<ExecResult, Error> =
-        match lang.get_compiler_command(&path_in_container, &out_path) {
+        match compiler_command {
             Some(command) => {
                 append_to_msg(&ctx, &mut reply, "Compiling code snippet...");
                 let commands = command.split("&&").map(|command| command.trim());
                 let mut res = Ok(ExecResult::default());
+                let mut commands_run = Vec::new();
                 info!("Compiling {} code", lang.get_lang_name());
-                for command in commands {
-                    let mut args = vec!["exec", "-w", "/home", &container_id];
-                    command.split(' ').for_each(|part| args.push(part));
-
-                    let cmd = duct::cmd("docker", args);
+                for (i, command) in commands.enumerate() {
+                    let mut step = Step::new(command, settings.compilation_timeout);
+                    step.output_limit = settings.output_limit;
+                    if i == 0 {
+                        // A flag of the user replaces the one of the same name, e.g. `--edition=2021`
+                        for flag in options.flags.iter() {
+                            if let Some(end) = flag.find('=') {
+                                step.command.retain(|arg| !arg.starts_with(&flag[..=end]));
+                            }
+                        }
+                        step.command.extend(options.flags.iter().cloned());
+                    }
+                    commands_run.push(step.command.join(" "));
 
-                    res = match run_command(cmd, settings.compilation_timeout) {
+                    res = match backend.run_step(&session, &step) {
                         Ok(res) => Ok(res),
                         Err(e) => {
                             cleanup();
-                            return Err(Error::new(
-                                ErrorKind::Other,
-                                format!("An error occurred while compiling code snippet: {}", e),
-                            ));
+                            return Err(Error::other(format!(
+                                "An error occurred while compiling code snippet: {}",
+                                e
+                            )));
                         }
                     };
                 }
+                if let Ok(ref mut res) = res {
+                    res.command = commands_run.join(" && ");
+                }
                 res
             }
             None => {
                 // For interpreted languages, we just copy the source file to the destination path
-                let cmd = cmd!(
-                    "docker",
-                    "cp",
-                    src_path.to_str().unwrap(),
-                    format!("{}:{}", container_id, out_path.to_str().unwrap())
-                );
-                let _ = cmd.run();
+                let _ = backend.copy_file(&session, &src_path, &out_path);
                 Ok(ExecResult::default())
             }
         };
@@ -279,15 +1104,45 @@ pub fn run_code(
         Ok(res) => res,
         Err(e) => {
             cleanup();
-            return Err(Error::new(
-                ErrorKind::Other,
-                format!("An error occurred while compiling code snippet: {}", e),
-            ));
+            return Err(Error::other(format!(
+                "An error occurred while compiling code snippet: {}",
+                e
+            )));
         }
     };
 
+    if let Some(emit) = options.emit {
+        let mut compilation = compilation;
+        if compilation.exit_code == Some(0) && !compilation.is_truncated() {
+            append_to_msg(&ctx, &mut reply, "Filtering output...");
+            // The listing is moved to the standard output, so that it is not shown twice
+            let emitted = if lang.is_emitted_to_stderr() {
+                compilation.stderr_raw.clear();
+                std::mem::take(&mut compilation.stderr)
+            } else {
+                compilation.stdout.clone()
+            };
+            let mut output = lang.filter_emitted_code(&emitted, emit);
+            if let Some(demangler) = lang.get_demangler() {
+                let mut step = Step::new(&demangler, settings.compilation_timeout);
+                step.stdin = Some(output.clone());
+                match backend.run_step(&session, &step) {
+                    Ok(res) if res.exit_code == Some(0) => output = res.stdout,
+                    Ok(_) => warn!("Could not demangle the {} of a snippet", emit.get_name()),
+                    Err(e) => warn!("Could not demangle the {} of a snippet: {}", emit.get_name(), e),
+                }
+            }
+            compilation.stdout_raw = output.clone().into_bytes();
+            compilation.stdout = output;
+        }
+
+        append_to_msg(&ctx, &mut reply, "Closing session...");
+        cleanup();
+        return Ok((compilation, ExecResult::default(), code, lang.get_lang_name()));
+    }
+
     // Execute code
-    let execution = if compilation.timed_out {
+    let execution = if compilation.timed_out || compilation.is_truncated() {
         ExecResult::default()
     } else {
         match compilation.exit_code {
@@ -299,20 +1154,70 @@ pub fn run_code(
                 // Compilation succeeded, run the snippet
                 append_to_msg(&ctx, &mut reply, "Running code snippet...");
                 info!("Executing {} code", lang.get_lang_name());
-                let exec_command = lang.get_execution_command(&out_path);
-                let mut args = vec!["exec", "-w", "/home", &container_id];
-                exec_command.split(' ').for_each(|part| args.push(part));
-                let cmd = duct::cmd("docker", args);
-                match run_command(cmd, settings.execution_timeout) {
-                    Ok(res) => res,
+                let mut step = Step::new(
+                    &lang.get_execution_command(&out_path),
+                    settings.execution_timeout,
+                );
+                if lang.get_compiler_command(&path_in_container, &out_path).is_none() {
+                    // Interpreted languages take the flags before the script
+                    for (i, flag) in options.flags.iter().enumerate() {
+                        step.command.insert(1 + i, flag.clone());
+                    }
+                }
+                step.command.extend(options.args.iter().cloned());
+                let command = step.command.join(" ");
+                step.output_limit = settings.output_limit;
+                step.stdin = options.stdin.clone();
+                step.env = options.env.clone();
+                let bench_step = step.clone();
+                step.measure_usage = true;
+                let res = match (ctx.as_deref(), reply.as_deref_mut()) {
+                    (Some(ctx), Some(msg)) if settings.stream_interval > 0 => {
+                        let (sender, receiver) = mpsc::channel();
+                        let interval = Duration::from_millis(settings.stream_interval);
+                        step.output_sender = Some(sender);
+                        thread::scope(|scope| {
+                            scope.spawn(move || stream_output(ctx, msg, receiver, interval));
+                            let res = backend.run_step(&session, &step);
+                            // Dropping the step closes the channel, which stops the streaming
+                            drop(step);
+                            res
+                        })
+                    }
+                    _ => backend.run_step(&session, &step),
+                };
+                let mut res = match res {
+                    Ok(res) => ExecResult { command, ..res },
                     Err(e) => {
                         cleanup();
-                        return Err(Error::new(
-                            ErrorKind::Other,
-                            format!("An error occurred while running code snippet: {}", e),
-                        ));
+                        return Err(Error::other(format!(
+                            "An error occurred while running code snippet: {}",
+                            e
+                        )));
                     }
+                };
+
+                // The first run is shown as usual and warms up the session, the benchmark comes after it
+                let succeeded = res.exit_code == Some(0) && !res.timed_out && !res.is_truncated();
+                if let (Some(runs), true) = (options.bench, succeeded) {
+                    let runs = runs.min(settings.bench_max_runs);
+                    append_to_msg(&ctx, &mut reply, &format!("Benchmarking code snippet ({} runs)...", runs));
+                    info!("Benchmarking {} code", lang.get_lang_name());
+                    let budget = Duration::from_secs(settings.execution_timeout)
+                        .checked_sub(res.duration)
+                        .unwrap_or_default();
+                    res.bench = match run_bench(backend, &session, &bench_step, runs, budget) {
+                        Ok(bench) => Some(bench),
+                        Err(e) => {
+                            cleanup();
+                            return Err(Error::other(format!(
+                                "An error occurred while benchmarking code snippet: {}",
+                                e
+                            )));
+                        }
+                    };
                 }
+                res
             }
         }
     };
@@ -324,11 +1229,49 @@ pub fn run_code(
 
 #[command]
 #[aliases("execute", "run", "code")]
-#[description = "Executes a code snippet. Your message needs to look like this:\r\n~exec\r\n\\`\\`\\`language\r\n\r\ncode...\r\n\\`\\`\\`\r\nwhere `language` is the language of your choice.\r\nFor example:\r\n~exec\r\n\\`\\`\\`javascript\r\nconsole.log(\"hi!\");\r\n\\`\\`\\`"]
+#[description = "Executes a code snippet. Your message needs to look like this:\r\n~exec\r\n\\`\\`\\`language\r\n\r\ncode...\r\n\\`\\`\\`\r\nwhere `language` is the language of your choice.\r\nFor example:\r\n~exec\r\n\\`\\`\\`javascript\r\nconsole.log(\"hi!\");\r\n\\`\\`\\`\r\nTo send several files, name each code section after its language, e.g. \\`\\`\\`c main.c, the first one being the main file.\r\nYou can also upload source files instead, their language is found from their extension.\r\nAdd a code section tagged `stdin` (or `input`) to feed its contents to the program's standard input.\r\nUse `--arg value` and `--env NAME=value` after the command to pass arguments and environment variables to the program, `--flag value` to pass an allowed flag to the compiler or interpreter, `--hex` to display its output as a hexdump, and `--emit asm|llvm-ir|mir` to see what the compiler produces instead of running it.\r\nEditing your message re-runs it and updates the result, react with \u{1f501} to re-run it or \u{1f5d1} to delete the result."]
+#[example = "--flag -O2 --arg foo --env RUST_BACKTRACE=1"]
+#[bucket = "exec_bucket"]
+fn exec(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    exec_message(ctx, msg, args.rest(), None)
+}
+
+#[command]
+#[description = "Runs a code snippet several times and reports how long it takes, like `~exec --bench 10`.\r\nThe snippet is compiled once, run a first time to show its output, then run again in the same container.\r\nUse `--bench N` to choose the number of runs, the other options of `~exec` are accepted as well."]
+#[example = "--bench 20"]
+#[bucket = "exec_bucket"]
+fn bench(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    exec_message(ctx, msg, &get_bench_args(args.rest()), None)
+}
+
+#[command]
+#[description = "Shows the assembly a compiler produces for a code snippet instead of running it, like `~exec --emit asm`.\r\nWorks with C, C++, Rust and Go. Use `--opt N` to choose the optimisation level (0, 1, 2, 3, s or z), and `~exec --emit llvm-ir` or `~exec --emit mir` to see the LLVM IR or MIR of Rust code."]
+#[example = "--opt 2"]
 #[bucket = "exec_bucket"]
-fn exec(ctx: &mut Context, msg: &Message) -> CommandResult {
-    let arg = msg.content.clone();
-    let split = arg.split("```");
+fn asm(ctx: &mut Context, msg: &Message, args: Args) -> CommandResult {
+    exec_message(ctx, msg, &get_asm_args(args.rest()), None)
+}
+
+/// Turns the arguments of an `asm` command into those of the equivalent `exec` command.
+fn get_asm_args(args: &str) -> String {
+    format!("--emit asm {}", args)
+}
+
+/// Turns the arguments of a `bench` command into those of the equivalent `exec` command.
+fn get_bench_args(args: &str) -> String {
+    // A `--bench` option given by the user comes after this one and overrides it
+    format!("--bench {} {}", DEFAULT_BENCH_RUNS, args)
+}
+
+/// Runs the snippet of an `exec` command whose arguments are `args`. When `reply` is given,
+/// the command is being re-run and its previous result is edited instead of sending a new one.
+pub fn exec_message(
+    ctx: &mut Context,
+    msg: &Message,
+    args: &str,
+    mut reply: Option<Message>,
+) -> CommandResult {
+    let blocks = get_code_blocks(&msg.content);
     let data = ctx.data.read();
     let settings = data.get::<Settings>().unwrap().lock().unwrap().clone();
 
@@ -340,74 +1283,113 @@ fn exec(ctx: &mut Context, msg: &Message) -> CommandResult {
         .get_languages_list();
     drop(data);
 
-    if split.clone().nth(1).is_none() {
-        let _ = msg.reply(&ctx, &format!("Please add a code section to your message\nExample:\n{}exec\n\\`\\`\\`language\n**code**\n\\`\\`\\`\nHere are the languages available: {}", settings.command_prefix, langs))?;
-        return Ok(());
-    }
-    let code = split.take(2).collect::<Vec<_>>()[1];
-
-    let mut split = code.split('\n');
-    let (lang_code, code) = match split.next() {
-        Some(line) => {
-            let code = split.collect::<Vec<_>>().join("\n");
-            let lang = line.trim().to_ascii_lowercase();
-            (lang, code)
+    let mut options = match parse_exec_options(args.split("```").next().unwrap_or("")) {
+        Ok(options) => options,
+        Err(e) => {
+            reply_error(ctx, msg, reply.as_mut(), &format!(":x: {}", e))?;
+            return Ok(());
         }
+    };
+    options.stdin = blocks
+        .iter()
+        .find(|block| block.is_stdin())
+        .map(|block| block.code.clone());
+    let mut blocks: Vec<CodeBlock> = blocks.into_iter().filter(|block| !block.is_stdin()).collect();
+    // Uploaded source files are used when the message has no code section
+    let from_attachments = blocks.is_empty() && !msg.attachments.is_empty();
+    if from_attachments {
+        blocks = match download_source_files(&msg.attachments, settings.source_file_limit) {
+            Ok(blocks) => blocks,
+            Err(e) => {
+                reply_error(ctx, msg, reply.as_mut(), &format!(":x: {}", e))?;
+                return Ok(());
+            }
+        };
+    }
+    let (lang_code, first_file) = match blocks.first() {
+        Some(block) => (block.tag.to_ascii_lowercase(), block.filename.clone().unwrap_or_default()),
         None => {
-            let _ = msg.reply(
-                &ctx,
-                &format!(
-                    ":x: Please specify a language\nHere are the languages available: {}",
-                    langs
-                ),
-            )?;
+            reply_error(ctx, msg, reply.as_mut(), &format!("Please add a code section to your message, or upload a source file\nExample:\n{}exec\n\\`\\`\\`language\n**code**\n\\`\\`\\`\nHere are the languages available: {}", settings.command_prefix, langs))?;
+            return Ok(());
+        }
+    };
+    let files = match get_source_files(blocks) {
+        Ok(files) => files,
+        Err(e) => {
+            reply_error(ctx, msg, reply.as_mut(), &format!(":x: {}", e))?;
             return Ok(());
         }
     };
+    let file_names: Vec<String> = files.iter().filter_map(|file| file.name.clone()).collect();
 
     let mut reply_msg: Message;
+    let mut rerun = false;
+    let mut snippets = Vec::new();
     let (mut compilation, mut execution, lang) = {
-        let lang = {
+        let (lang, backend) = {
             // We make sure to lock the data in a separate code block,
             // Otherwise we would block the mutex through the entire compiling and/or executing phases
             let data = ctx.data.read();
             let mngr = data.get::<LangManager>().unwrap().lock().unwrap();
-            get_lang(&mngr, lang_code.as_ref())
+            let lang = match options.lang {
+                Some(ref lang_code) => get_lang(&mngr, lang_code).map(|lang| (lang, None)),
+                None if from_attachments => get_lang_by_filename(&mngr, &first_file).map(|lang| (lang, None)),
+                None => get_lang_or_detect(&mngr, &lang_code, &files[0].code),
+            };
+            (lang, mngr.get_backend())
         };
         let lang = match lang {
-            Ok(lang) => lang,
+            Ok((lang, Some(detection))) if detection.confidence == Confidence::Low => {
+                ask_lang_confirmation(ctx, msg, reply, &settings, &lang, detection.lang_code)?;
+                return Ok(());
+            }
+            Ok((lang, _)) => lang,
             Err(e) => {
-                let _ = msg.reply(&ctx, &format!(":x: {}", e))?;
+                reply_error(ctx, msg, reply.as_mut(), &format!(":x: {}", e))?;
                 return Ok(());
             }
         };
 
         {
-            let data = ctx.data.read();
+            let mut data = ctx.data.write();
+            if let Some(executions) = data.get_mut::<LastExecutions>() {
+                executions.insert(msg.author.id, Instant::now());
+            }
             let db = data.get::<DbPool>().unwrap();
-            match models::Snippet::save(
-                code.clone(),
-                &lang.get_lang_name(),
-                msg.author.id,
-                msg.guild_id,
-                db,
-            ) {
-                Ok(_) => {}
-                Err(e) => warn!("Could not save snippet to db: {}", e),
-            };
+            for file in files.iter() {
+                match models::Snippet::save(
+                    file.code.clone(),
+                    &lang.get_lang_name(),
+                    msg.author.id,
+                    msg.guild_id,
+                    db,
+                ) {
+                    Ok(id) => snippets.push(id),
+                    Err(e) => warn!("Could not save snippet to db: {}", e),
+                };
+            }
         }
 
-        reply_msg = match msg.channel_id.say(&ctx, format!("<@{}>,", msg.author.id)) {
-            Err(e) => {
-                error!("An error occured while replying to an exec query: {}", e);
-                return Ok(());
+        reply_msg = match reply.take() {
+            Some(mut previous) => {
+                let _ = previous.edit(&ctx, |m| m.content(format!("<@{}>, re-running...", msg.author.id)));
+                rerun = true;
+                previous
             }
-            Ok(msg) => msg,
+            None => match msg.channel_id.say(&ctx, format!("<@{}>,", msg.author.id)) {
+                Err(e) => {
+                    error!("An error occured while replying to an exec query: {}", e);
+                    return Ok(());
+                }
+                Ok(msg) => msg,
+            },
         };
         match run_code(
             &settings,
-            code,
+            backend.as_ref(),
+            files,
             lang.clone(),
+            &options,
             msg.author.id,
             Some(ctx),
             Some(&mut reply_msg),
@@ -416,12 +1398,15 @@ fn exec(ctx: &mut Context, msg: &Message) -> CommandResult {
                 (compilation, execution, lang)
             }
             Err(e) => {
-                let _ = msg.reply(&ctx, &e.to_string())?;
+                let previous = if rerun { Some(&mut reply_msg) } else { None };
+                reply_error(ctx, msg, previous, &e.to_string())?;
                 return Ok(());
             }
         }
     };
 
+    // The limits of the language are the ones that killed the program
+    let lang_settings = settings.for_language(&lang.get_lang_name());
     compilation.stderr = pre_process_output(compilation.stderr);
     compilation.stdout = pre_process_output(compilation.stdout);
     execution.stderr = pre_process_output(execution.stderr);
@@ -432,136 +1417,598 @@ fn exec(ctx: &mut Context, msg: &Message) -> CommandResult {
         let db = data.get::<DbPool>().unwrap();
         let mut stat = models::LangStat::get(&lang.get_lang_name(), db);
         stat.increment_snippets_count(db);
+
+        if let Some(usage) = execution.usage {
+            if let Err(e) = models::Snippet::set_usage(
+                &snippets,
+                usage.peak_memory,
+                usage.user_time,
+                usage.system_time,
+                db,
+            ) {
+                warn!("Could not save snippet resource usage to db: {}", e);
+            }
+        }
     }
 
     let header = format!("<@{}>,", msg.author.id);
-    if let Err(why) = reply_msg.edit(ctx, |m| {
-        m.content(header).embed(|mut e| {
-            let mut fields_lang = vec![("Language", lang.get_lang_name(), true)];
-            let mut fields_out = Vec::<(&str, String, bool)>::new();
-            let mut color_red = false;
-
-            let compil_t = (compilation.duration.as_millis() as f32) / 1000.0_f32;
-            let exec_t = (execution.duration.as_millis() as f32) / 1000.0_f32;
-            let mut fields_time = Vec::<(&str, String, bool)>::new();
-            if compil_t > 0.0001 {
-                fields_time.push(("Compilation time", format!("{:.1}s", compil_t), true));
-            }
-            if exec_t > 0.0001 {
-                fields_time.push(("Execution time", format!("{:.1}s", exec_t), true));
+    let mut attachments = Attachments::new(settings.attachment_limit);
+    let mut embed = CreateEmbed::default();
+    {
+        let mut e = &mut embed;
+        let mut fields_lang = vec![("Language", lang.get_lang_name(), true)];
+        if let Some(emit) = options.emit {
+            let output = match options.opt_level {
+                Some(ref level) => format!("{} (opt level {})", emit.get_name(), level),
+                None => emit.get_name().to_string(),
+            };
+            fields_lang.push(("Output", output, true));
+        }
+        if !options.args.is_empty() {
+            let args = options.args.iter().map(|arg| format!("`{}`", pre_process_output(arg.clone())));
+            fields_lang.push(("Arguments", args.collect::<Vec<_>>().join(" "), true));
+        }
+        if !options.env.is_empty() {
+            let env = options.env.iter().map(|(key, val)| format!("`{}={}`", key, pre_process_output(val.clone())));
+            fields_lang.push(("Environment", env.collect::<Vec<_>>().join("\n"), true));
+        }
+        if !options.flags.is_empty() {
+            let command = if compilation.command.is_empty() {
+                &execution.command
+            } else {
+                &compilation.command
+            };
+            fields_lang.push(("Command", format!("`{}`", pre_process_output(command.clone())), false));
+        }
+        let mut fields_out = Vec::<(String, String, bool)>::new();
+        let mut color_red = false;
+
+        let compil_t = (compilation.duration.as_millis() as f32) / 1000.0_f32;
+        let exec_t = (execution.duration.as_millis() as f32) / 1000.0_f32;
+        let mut fields_time = Vec::<(&str, String, bool)>::new();
+        if compil_t > 0.0001 {
+            fields_time.push(("Compilation time", format!("{:.1}s", compil_t), true));
+        }
+        if exec_t > 0.0001 {
+            fields_time.push(("Execution time", format!("{:.1}s", exec_t), true));
+        }
+        if let Some(usage) = execution.usage {
+            fields_time.push(("Peak memory", format_memory(usage.peak_memory), true));
+            fields_time.push((
+                "CPU time",
+                format!(
+                    "{:.2}s user, {:.2}s system",
+                    usage.user_time.as_secs_f32(),
+                    usage.system_time.as_secs_f32()
+                ),
+                true,
+            ));
+        }
+        if let Some(ref bench) = execution.bench {
+            let mut text = if bench.runs.is_empty() {
+                String::from("No run completed")
+            } else {
+                format!(
+                    "{} runs: min {}, median {}, max {}, stddev {}",
+                    bench.runs.len(),
+                    format_bench_duration(bench.min()),
+                    format_bench_duration(bench.median()),
+                    format_bench_duration(bench.max()),
+                    format_bench_duration(bench.stddev()),
+                )
+            };
+            if let Some(ref reason) = bench.stopped {
+                text.push_str(&format!("\nStopped after {} of {} runs: {}", bench.runs.len(), bench.requested, reason));
             }
+            fields_time.push(("Benchmark", text, false));
+        }
 
-            if compilation.timed_out {
-                // Compilation timed out
+        if compilation.timed_out {
+            // Compilation timed out
+            e = e
+                .description(":x: Compilation timed out")
+                .colour(serenity::utils::Colour::RED);
+            color_red = true;
+        }
+        if execution.timed_out {
+            // Execution timed out
+            e = e
+                .description(":x: Execution timed out")
+                .colour(serenity::utils::Colour::RED);
+            color_red = true;
+        }
+        if compilation.is_truncated() || execution.is_truncated() {
+            // Output limit exceeded
+            let dropped = compilation.stdout_dropped
+                + compilation.stderr_dropped
+                + execution.stdout_dropped
+                + execution.stderr_dropped;
+            e = e
+                .description(format!(
+                    ":x: Output limit exceeded, the program was stopped ({} bytes dropped)",
+                    dropped
+                ))
+                .colour(serenity::utils::Colour::RED);
+            color_red = true;
+        }
+        let termination = compilation
+            .termination
+            .map(|termination| format!("Compilation: {}", termination.describe(&lang_settings)))
+            .or_else(|| execution.termination.map(|termination| termination.describe(&lang_settings)));
+        match compilation.exit_code {
+            Some(code) if code != 0 => {
+                // Compilation failed
                 e = e
-                    .description(":x: Compilation timed out")
+                    .description(":x: Compilation failed")
                     .colour(serenity::utils::Colour::RED);
-                color_red = true;
+
+                if file_names.len() > 1 {
+                    let output = format!("{}\n{}", compilation.stdout, compilation.stderr);
+                    let counts = count_diagnostics_by_file(&output, &file_names)
+                        .into_iter()
+                        .map(|(name, count)| format!("`{}`: {}", name, count));
+                    fields_lang.push(("Diagnostics by file", counts.collect::<Vec<_>>().join("\n"), true));
+                }
+
+                fields_out.push(attachments.output_field(
+                    "Compilation error output",
+                    compilation.stderr,
+                    &compilation.stderr_raw,
+                    compilation.stderr_dropped,
+                    "compilation-stderr.txt",
+                ));
             }
-            if execution.timed_out {
-                // Execution timed out
-                e = e
-                    .description(":x: Execution timed out")
-                    .colour(serenity::utils::Colour::RED);
-                color_red = true;
-            }
-            match compilation.exit_code {
-                Some(code) if code != 0 => {
-                    // Compilation failed
-                    e = e
-                        .description(":x: Compilation failed")
-                        .colour(serenity::utils::Colour::RED);
-
-                    let (truncated, out) = format_code_output(compilation.stderr, 1024);
-                    let label = if truncated {
-                        "Compilation error output (truncated)"
-                    } else {
-                        "Compilation error output"
-                    };
-                    fields_out.push((label, out, false));
+            _ => {
+                // Compilation succeeded
+                if !color_red {
+                    e = e.colour(serenity::utils::Colour::DARK_GREEN);
                 }
-                _ => {
-                    // Compilation succeeded
-                    if !color_red {
-                        e = e.colour(serenity::utils::Colour::DARK_GREEN);
-                    }
 
-                    if !compilation.stdout.is_empty() {
-                        let (truncated, out) = format_code_output(compilation.stdout, 1024);
-                        let label = if truncated {
-                            "Compilation output (truncated)"
-                        } else {
-                            "Compilation output"
-                        };
-                        fields_out.push((label, out, false));
-                    }
-                    if !compilation.stderr.is_empty() {
-                        if !color_red {
-                            e = e.colour(serenity::utils::Colour::ORANGE);
-                        }
-                        let (truncated, out) = format_code_output(compilation.stderr, 1024);
-                        let label = if truncated {
-                            "Compilation error output (truncated)"
-                        } else {
-                            "Compilation error output"
-                        };
-                        fields_out.push((label, out, false));
+                if let Some(emit) = options.emit {
+                    let filename = format!("output.{}", emit.get_extension());
+                    let (view, note) = attachments.output_view(
+                        compilation.stdout.clone(),
+                        emit.get_syntax(),
+                        &compilation.stdout_raw,
+                        &filename,
+                    );
+                    e = e.description(view);
+                    if let Some(note) = note {
+                        e = e.footer(|f| f.text(note));
                     }
-                    if let Some(code) = execution.exit_code {
-                        fields_lang.push(("Exit code", format!("`{}`", code), true));
-                    }
-                    if !execution.stdout.is_empty() {
-                        let (truncated, out) = format_code_output(execution.stdout, 1024);
-                        let label = if truncated {
-                            "Standard output (truncated)"
-                        } else {
-                            "Standard output"
-                        };
-                        fields_out.push((label, out, false));
-                    }
-                    if !execution.stderr.is_empty() {
-                        e = e.colour(serenity::utils::Colour::RED);
-                        let (truncated, out) = format_code_output(execution.stderr, 1024);
-                        let label = if truncated {
-                            "Error output (truncated)"
-                        } else {
-                            "Error output"
-                        };
-                        fields_out.push((label, out, false));
+                } else if !compilation.stdout.is_empty() {
+                    fields_out.push(attachments.output_field(
+                        "Compilation output",
+                        compilation.stdout,
+                        &compilation.stdout_raw,
+                        compilation.stdout_dropped,
+                        "compilation-stdout.txt",
+                    ));
+                }
+                if !compilation.stderr.is_empty() {
+                    if !color_red {
+                        e = e.colour(serenity::utils::Colour::ORANGE);
                     }
+                    fields_out.push(attachments.output_field(
+                        "Compilation error output",
+                        compilation.stderr,
+                        &compilation.stderr_raw,
+                        compilation.stderr_dropped,
+                        "compilation-stderr.txt",
+                    ));
                 }
-            };
+                if let Some(code) = execution.exit_code {
+                    fields_lang.push(("Exit code", format!("`{}`", code), true));
+                }
+                if !execution.stdout_raw.is_empty() {
+                    fields_out.push(if options.hex {
+                        attachments.output_field(
+                            "Standard output (hex)",
+                            format_hexdump(&execution.stdout_raw),
+                            &execution.stdout_raw,
+                            execution.stdout_dropped,
+                            "stdout.bin",
+                        )
+                    } else {
+                        attachments.output_field(
+                            "Standard output",
+                            execution.stdout,
+                            &execution.stdout_raw,
+                            execution.stdout_dropped,
+                            "stdout.txt",
+                        )
+                    });
+                }
+                if !execution.stderr_raw.is_empty() {
+                    e = e.colour(serenity::utils::Colour::RED);
+                    fields_out.push(if options.hex {
+                        attachments.output_field(
+                            "Error output (hex)",
+                            format_hexdump(&execution.stderr_raw),
+                            &execution.stderr_raw,
+                            execution.stderr_dropped,
+                            "stderr.bin",
+                        )
+                    } else {
+                        attachments.output_field(
+                            "Error output",
+                            execution.stderr,
+                            &execution.stderr_raw,
+                            execution.stderr_dropped,
+                            "stderr.txt",
+                        )
+                    });
+                }
+                if execution.invalid_utf8 && !options.hex {
+                    e = e.footer(|f| f.text("The output contained invalid UTF-8, which was replaced. Use --hex to see the raw bytes."));
+                }
+            }
+        };
+        if let Some(termination) = termination {
+            // Crashes are more telling than a failure or an exit code
+            e = e
+                .description(format!(":x: {}", termination))
+                .colour(serenity::utils::Colour::RED);
+        }
+
+        e = e.fields(fields_lang).fields(fields_time);
+        if !fields_out.is_empty() {
+            e = e.fields(fields_out)
+        }
 
-            e = e.fields(fields_lang).fields(fields_time);
-            if !fields_out.is_empty() {
-                e = e.fields(fields_out)
+        e.author(|a| a.name(&msg.author.name).icon_url(&msg.author.face()))
+            .timestamp(chrono::Utc::now().to_rfc3339())
+            .thumbnail(lang.get_logo_url());
+    }
+
+    if attachments.files.is_empty() {
+        if let Err(why) = reply_msg.edit(&ctx, |m| {
+            m.content(header).embed(|e| {
+                e.0 = embed.0;
+                e
+            })
+        }) {
+            error!(
+                "An error occured while editing a reply to an exec query: {:?}",
+                why
+            );
+            return Ok(());
+        }
+    } else {
+        // Attachments cannot be added to an existing message, the reply is sent again with them
+        let files = attachments.files.iter().map(|(filename, data)| AttachmentType::Bytes {
+            data: Cow::Borrowed(data),
+            filename: filename.clone(),
+        });
+        let sent = msg.channel_id.send_files(&ctx.http, files, |m| {
+            m.content(header).embed(|e| {
+                e.0 = embed.0;
+                e
+            })
+        });
+        match sent {
+            Ok(sent) => {
+                let _ = reply_msg.delete(&ctx);
+                reply_msg = sent;
+            }
+            Err(why) => {
+                error!(
+                    "An error occured while replying to an exec query with attachments: {:?}",
+                    why
+                );
+                return Ok(());
             }
+        }
+    }
 
-            e.author(|a| a.name(&msg.author.name).icon_url(&msg.author.face()))
-                .timestamp(chrono::Utc::now().to_rfc3339())
-                .thumbnail(lang.get_logo_url())
-        })
-    }) {
-        error!(
-            "An error occured while editing a reply to an exec query: {:?}",
-            why
-        );
-        return Ok(());
+    if settings.rerun_window > 0 {
+        let mut data = ctx.data.write();
+        if let Some(replies) = data.get_mut::<ExecReplies>() {
+            let entry = replies.entry(msg.id).or_insert_with(|| ExecReply {
+                reply_id: reply_msg.id,
+                channel_id: msg.channel_id,
+                created: Instant::now(),
+                snippets: Vec::new(),
+                pending_lang: None,
+            });
+            entry.reply_id = reply_msg.id;
+            entry.snippets.extend(snippets);
+            entry.pending_lang = None;
+        }
+        drop(data);
+
+        for reaction in [RERUN_REACTION, DELETE_REACTION].iter() {
+            if let Err(why) = reply_msg.react(&ctx, ReactionType::Unicode(reaction.to_string())) {
+                warn!("Could not add a reaction to an exec reply: {:?}", why);
+            }
+        }
     }
 
     info!("Done");
     Ok(())
 }
 
-fn format_code_output(mut text: String, max_length: usize) -> (bool, String) {
-    let truncated = if text.len() > max_length - 7 {
-        text.truncate(max_length - 7);
+/// Asks the author of an `exec` command whether the language guessed for their snippet is the right
+/// one, instead of running it. Their answer is a reaction to the question, see `CONFIRM_REACTION`.
+fn ask_lang_confirmation(
+    ctx: &mut Context,
+    msg: &Message,
+    reply: Option<Message>,
+    settings: &Settings,
+    lang: &BoxedLang,
+    lang_code: &str,
+) -> CommandResult {
+    let mut question = format!("This looks like {} code, but I am not sure.", lang.get_lang_name());
+    if settings.rerun_window > 0 {
+        question.push_str(&format!(" React with {} to run it as {},", CONFIRM_REACTION, lang.get_lang_name()));
+    }
+    question.push_str(&format!(
+        " or add the language after the \\`\\`\\`, e.g. \\`\\`\\`{}",
+        lang_code
+    ));
+
+    let question_msg = match reply {
+        Some(mut previous) => {
+            previous.edit(&ctx, |m| {
+                m.content(format!("<@{}>,", msg.author.id))
+                    .embed(|e| e.description(&question).colour(serenity::utils::Colour::ORANGE))
+            })?;
+            previous
+        }
+        None => msg.channel_id.say(&ctx, format!("<@{}>, {}", msg.author.id, question))?,
+    };
+
+    if settings.rerun_window > 0 {
+        let mut data = ctx.data.write();
+        if let Some(replies) = data.get_mut::<ExecReplies>() {
+            let entry = replies.entry(msg.id).or_insert_with(|| ExecReply {
+                reply_id: question_msg.id,
+                channel_id: msg.channel_id,
+                created: Instant::now(),
+                snippets: Vec::new(),
+                pending_lang: None,
+            });
+            entry.reply_id = question_msg.id;
+            entry.pending_lang = Some(lang_code.to_string());
+        }
+        drop(data);
+
+        if let Err(why) = question_msg.react(&ctx, ReactionType::Unicode(CONFIRM_REACTION.to_string())) {
+            warn!("Could not add a reaction to an exec reply: {:?}", why);
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds the language confirmed by the author of an `exec` command to its arguments.
+pub fn get_confirmed_lang_args(args: &str, lang_code: &str) -> String {
+    // A `--lang` option given by the user comes after this one and overrides it
+    format!("--lang {} {}", lang_code, args)
+}
+
+/// Reaction letting the author of an `exec` command run it again.
+pub const RERUN_REACTION: &str = "\u{1f501}";
+/// Reaction letting the author of an `exec` command, or a guild admin, delete its result.
+pub const DELETE_REACTION: &str = "\u{1f5d1}";
+/// Reaction letting the author of an `exec` command run it in the language guessed for it.
+pub const CONFIRM_REACTION: &str = "\u{2705}";
+
+/// The result of a recent `exec` command, which is re-run when the command is edited.
+pub struct ExecReply {
+    pub reply_id: MessageId,
+    pub channel_id: ChannelId,
+    pub created: Instant,
+    /// Ids of the snippets stored for each run of the command.
+    pub snippets: Vec<i32>,
+    /// Code of the language guessed for the snippet, when the author was asked to confirm it.
+    pub pending_lang: Option<String>,
+}
+
+/// Removes the replies older than `window` from `replies`, and returns them so that their
+/// reactions can be taken back.
+pub fn take_expired_replies(replies: &mut HashMap<MessageId, ExecReply>, window: Duration) -> Vec<ExecReply> {
+    let expired: Vec<MessageId> = replies
+        .iter()
+        .filter(|(_, reply)| reply.created.elapsed() >= window)
+        .map(|(command_id, _)| *command_id)
+        .collect();
+    expired
+        .iter()
+        .filter_map(|command_id| replies.remove(command_id))
+        .collect()
+}
+
+/// Finds the `exec` command whose result is `reply_id`, among the recent ones.
+pub fn find_exec_command(replies: &HashMap<MessageId, ExecReply>, reply_id: MessageId) -> Option<MessageId> {
+    replies
+        .iter()
+        .find(|(_, reply)| reply.reply_id == reply_id)
+        .map(|(command_id, _)| *command_id)
+}
+
+/// Results of the recent `exec` commands, by command message.
+pub struct ExecReplies;
+
+impl Key for ExecReplies {
+    type Value = HashMap<MessageId, ExecReply>;
+}
+
+/// Seconds users wait between two executions, by `exec_bucket` for commands and by
+/// `get_rerun_delay` for re-runs.
+pub const EXEC_DELAY: u64 = 5;
+
+/// When each user last ran a snippet, whether by a command or a re-run.
+pub struct LastExecutions;
+
+impl Key for LastExecutions {
+    type Value = HashMap<UserId, Instant>;
+}
+
+/// Returns how many seconds `user` has to wait before re-running a snippet, if they ran one
+/// less than `EXEC_DELAY` seconds ago.
+pub fn get_rerun_delay(executions: &HashMap<UserId, Instant>, user: UserId) -> Option<u64> {
+    let elapsed = executions.get(&user)?.elapsed();
+    let delay = Duration::from_secs(EXEC_DELAY);
+    if elapsed >= delay {
+        return None;
+    }
+    // Rounded up, so that waiting as long as told is enough
+    let left = delay - elapsed;
+    Some(left.as_secs() + u64::from(left.subsec_nanos() > 0))
+}
+
+/// Returns the arguments of `content` if it is an `exec`, a `bench` or an `asm` command.
+pub fn get_exec_args(content: &str, prefix: &str) -> Option<String> {
+    let content = content.trim_start();
+    if !content.starts_with(prefix) {
+        return None;
+    }
+    let content = &content[prefix.len()..];
+    let name_end = content
+        .find(|c: char| c.is_whitespace() || c == '`')
+        .unwrap_or(content.len());
+    match content[..name_end].to_ascii_lowercase().as_str() {
+        "exec" | "execute" | "run" | "code" => Some(content[name_end..].to_string()),
+        "bench" => Some(get_bench_args(&content[name_end..])),
+        "asm" => Some(get_asm_args(&content[name_end..])),
+        _ => None,
+    }
+}
+
+/// Answers an `exec` command with an error, in place of its previous result when it is re-run.
+fn reply_error(
+    ctx: &Context,
+    msg: &Message,
+    previous: Option<&mut Message>,
+    text: &str,
+) -> serenity::Result<()> {
+    match previous {
+        Some(previous) => previous.edit(ctx, |m| {
+            m.content(format!("<@{}>,", msg.author.id)).embed(|e| {
+                e.description(text)
+                    .colour(serenity::utils::Colour::RED)
+                    .author(|a| a.name(&msg.author.name).icon_url(&msg.author.face()))
+            })
+        }),
+        None => msg.reply(ctx, text).map(|_| ()),
+    }
+}
+
+/// Output streams that did not fit in the result embed, sent as files along with it.
+pub struct Attachments {
+    pub files: Vec<(String, Vec<u8>)>,
+    /// Maximum size of each file, 0 disables the attachments
+    limit: usize,
+}
+
+impl Attachments {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            files: Vec::new(),
+            limit,
+        }
+    }
+
+    /// Formats an output stream as an embed field, noting in its label when it was truncated.
+    /// When it does not fit in the field, the whole stream is attached as `filename`.
+    pub fn output_field(
+        &mut self,
+        label: &str,
+        text: String,
+        raw: &[u8],
+        dropped: usize,
+        filename: &str,
+    ) -> (String, String, bool) {
+        let (truncated, out) = format_code_output(text, 1024);
+        let label = if truncated && self.limit > 0 {
+            format!("{} ({})", label, self.attach(raw, filename))
+        } else if truncated || dropped > 0 {
+            format!("{} (truncated)", label)
+        } else {
+            label.to_string()
+        };
+
+        (label, out, false)
+    }
+
+    /// Formats a long output as a highlighted code block for the embed description. When it
+    /// does not fit, the whole output is attached as `filename` and a note tells so.
+    pub fn output_view(
+        &mut self,
+        text: String,
+        syntax: &str,
+        raw: &[u8],
+        filename: &str,
+    ) -> (String, Option<String>) {
+        let (truncated, view) = format_code_block(text, syntax, OUTPUT_VIEW_LENGTH);
+        let note = if truncated && self.limit > 0 {
+            Some(format!("Output {}", self.attach(raw, filename)))
+        } else if truncated {
+            Some("Output truncated".to_string())
+        } else {
+            None
+        };
+
+        (view, note)
+    }
+
+    /// Attaches `raw` as `filename`, up to the limit, and tells how much of it was attached.
+    fn attach(&mut self, raw: &[u8], filename: &str) -> String {
+        let mut data = raw.to_vec();
+        let note = if data.len() > self.limit {
+            data.truncate(self.limit);
+            format!("truncated, first {} bytes in {}", self.limit, filename)
+        } else {
+            format!("truncated, full output in {}", filename)
+        };
+        self.files.push((filename.to_string(), data));
+        note
+    }
+}
+
+/// Renders bytes like `hexdump -C`, with 8 bytes per line to fit in an embed.
+pub fn format_hexdump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (i, line) in bytes.chunks(8).enumerate() {
+        let hex: Vec<String> = line.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let ascii: String = line
+            .iter()
+            .map(|&byte| match byte {
+                // Backticks and mentions could break out of the code block
+                b'`' | b'@' => '.',
+                0x20..=0x7e => byte as char,
+                _ => '.',
+            })
+            .collect();
+        dump.push_str(&format!("{:08x}  {:<23}  |{}|\n", i * 8, hex.join(" "), ascii));
+    }
+
+    dump
+}
+
+/// Maximum length of an output shown in the description of the result embed.
+pub const OUTPUT_VIEW_LENGTH: usize = 2000;
+
+fn format_code_output(text: String, max_length: usize) -> (bool, String) {
+    format_code_block(text, "", max_length)
+}
+
+/// Wraps `text` in a code block highlighted as `syntax`, truncated to fit in `max_length`.
+pub fn format_code_block(mut text: String, syntax: &str, max_length: usize) -> (bool, String) {
+    let overhead = 7 + syntax.len();
+    let truncated = if text.len() > max_length - overhead {
+        let mut end = max_length - overhead;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
         true
     } else {
         false
     };
 
-    text = format!("```\n{}```", text);
+    text = format!("```{}\n{}```", syntax, text);
     (truncated, text)
 }
 
@@ -584,8 +2031,14 @@ fn get_random_filename(ext: &str) -> String {
 
 pub fn get_snippets_directory() -> Result<PathBuf, Error> {
     let mut dir = PathBuf::new();
-    dir.push(env::current_dir()?);
-    dir.push("snippets");
+    // Tests save their snippets out of the working tree
+    if cfg!(test) {
+        dir.push(env::temp_dir());
+        dir.push("rustacean-test-snippets");
+    } else {
+        dir.push(env::current_dir()?);
+        dir.push("snippets");
+    }
     if !dir.exists() {
         fs::create_dir_all(&dir)?;
     }
@@ -603,7 +2056,7 @@ pub fn get_snippets_directory_for_user(user: UserId) -> Result<PathBuf, Error> {
     Ok(dir)
 }
 
-fn save_code(code: &str, author: UserId, ext: &str) -> Result<PathBuf, Error> {
+pub fn save_code(code: &str, author: UserId, ext: &str) -> Result<PathBuf, Error> {
     let mut path = get_snippets_directory_for_user(author)?;
 
     loop {
@@ -616,51 +2069,3 @@ fn save_code(code: &str, author: UserId, ext: &str) -> Result<PathBuf, Error> {
 
     Ok(path)
 }
-
-fn run_command(cmd: Expression, timeout_seconds: u64) -> Result<ExecResult, Error> {
-    let child = cmd
-        .unchecked() // important! allows us to get stderr instead of an `Error` if the process exits with a non-zero exit code
-        .stdout_capture()
-        .stderr_capture()
-        .start()?;
-
-    let timeout = Duration::from_secs(timeout_seconds);
-    let start = Instant::now();
-
-    loop {
-        if child.try_wait()?.is_some() {
-            break;
-        }
-
-        if timeout_seconds != 0 && start.elapsed() >= timeout {
-            child.kill()?;
-
-            return Ok(ExecResult {
-                exit_code: None,
-                stdout: "".into(),
-                stderr: "".into(),
-                timed_out: true,
-                duration: start.elapsed(),
-            });
-        }
-
-        ::std::thread::sleep(Duration::from_millis(250));
-    }
-
-    let output = child.wait()?;
-
-    let stdout = ::std::str::from_utf8(&output.stdout)
-        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?
-        .to_owned();
-    let stderr = ::std::str::from_utf8(&output.stderr)
-        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?
-        .to_owned();
-
-    Ok(ExecResult {
-        exit_code: output.status.code(),
-        stdout,
-        stderr,
-        timed_out: false,
-        duration: start.elapsed(),
-    })
-}
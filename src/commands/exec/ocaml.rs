@@ -22,6 +22,10 @@ impl Language for OCaml {
     format!("ocaml {}", path.to_str().unwrap())
   }
 
+  fn get_formatter_command(&self) -> Option<String> {
+    Some("ocamlformat --enable-outside-detected-project --impl -".into())
+  }
+
   fn check_compiler_or_interpreter(&self) -> String {
     "ocaml --version".into()
   }
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-use crate::commands::exec::language::Language;
+use crate::commands::exec::language::{read_package_list, Language, PACKAGES_DIRECTORY};
 
 #[derive(Debug)]
 pub struct Python;
@@ -32,6 +32,29 @@ impl Language for Python {
         format!("{} {}", self.get_interpreter(), path.to_str().unwrap())
     }
 
+    fn get_allowed_flags(&self) -> Vec<String> {
+        ["-O", "-OO", "-B", "-u", "-Werror", "-Xdev"]
+            .iter()
+            .map(|flag| flag.to_string())
+            .collect()
+    }
+
+    fn get_packages(&self) -> Vec<(String, String)> {
+        let path = format!("{}/python/requirements.txt", PACKAGES_DIRECTORY);
+        read_package_list(&path, "==")
+    }
+
+    fn get_formatter_command(&self) -> Option<String> {
+        Some("black --quiet -".into())
+    }
+
+    fn get_linter_command(&self, src_path: &PathBuf) -> Option<String> {
+        Some(format!(
+            "ruff check --no-cache --output-format=concise {}",
+            src_path.to_str().unwrap()
+        ))
+    }
+
     fn check_compiler_or_interpreter(&self) -> String {
         format!("{} --version", self.get_interpreter())
     }
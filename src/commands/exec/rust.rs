@@ -1,6 +1,10 @@
+use std::fs;
 use std::path::PathBuf;
 
-use crate::commands::exec::language::Language;
+use crate::commands::exec::language::{Emit, Language, PACKAGES_DIRECTORY};
+
+/// Edition of the snippets, with or without packages, unless `--edition=` is given as a flag.
+const DEFAULT_EDITION: &str = "2018";
 
 #[derive(Debug)]
 pub struct Rust;
@@ -32,12 +36,129 @@ impl Language for Rust {
 
     fn get_compiler_command(&self, src_path: &PathBuf, exe_path: &PathBuf) -> Option<String> {
         Some(format!(
-            "rustc {} -o {}",
+            "rustc --edition={} {} -o {}",
+            DEFAULT_EDITION,
             src_path.to_str().unwrap(),
             exe_path.to_str().unwrap()
         ))
     }
 
+    fn get_allowed_flags(&self) -> Vec<String> {
+        [
+            "-O",
+            "-g",
+            "--edition=",
+            "-Copt-level=",
+            "-Cdebug-assertions=",
+            "-Coverflow-checks=",
+            "-Cpanic=",
+            "-Ctarget-cpu=native",
+        ]
+        .iter()
+        .map(|flag| flag.to_string())
+        .collect()
+    }
+
+    fn get_packages_compiler_command(
+        &self,
+        code: &str,
+        src_paths: &[PathBuf],
+        exe_path: &PathBuf,
+    ) -> Option<String> {
+        use regex::Regex;
+
+        let uses_packages = self.get_packages().iter().any(|(name, _)| {
+            let name = regex::escape(&name.replace('-', "_"));
+            let re = Regex::new(&format!(r"\b{}::|extern\s+crate\s+{}\b", name, name)).unwrap();
+            re.is_match(code)
+        });
+        if !uses_packages {
+            return None;
+        }
+
+        Some(format!(
+            "rustc-packages --edition={} {} -o {}",
+            DEFAULT_EDITION,
+            src_paths[0].to_str().unwrap(),
+            exe_path.to_str().unwrap()
+        ))
+    }
+
+    fn get_packages(&self) -> Vec<(String, String)> {
+        let path = format!("{}/rust/Cargo.toml", PACKAGES_DIRECTORY);
+        let manifest =
+            match fs::read_to_string(&path).map(|manifest| manifest.parse::<toml::Value>()) {
+                Ok(Ok(manifest)) => manifest,
+                Ok(Err(e)) => {
+                    warn!("Could not parse {}: {}", path, e);
+                    return Vec::new();
+                }
+                Err(e) => {
+                    warn!("Could not read {}: {}", path, e);
+                    return Vec::new();
+                }
+            };
+        let dependencies = match manifest
+            .get("dependencies")
+            .and_then(|deps| deps.as_table())
+        {
+            Some(dependencies) => dependencies,
+            None => return Vec::new(),
+        };
+        dependencies
+            .iter()
+            .map(|(name, dependency)| {
+                let version = match dependency {
+                    toml::Value::String(version) => Some(version.as_str()),
+                    dependency => dependency
+                        .get("version")
+                        .and_then(|version| version.as_str()),
+                };
+                (name.clone(), version.unwrap_or("*").to_string())
+            })
+            .collect()
+    }
+
+    fn get_emit_kinds(&self) -> Vec<Emit> {
+        vec![Emit::Asm, Emit::LlvmIr, Emit::Mir]
+    }
+
+    fn get_emit_command(
+        &self,
+        src_path: &PathBuf,
+        emit: Emit,
+        opt_level: Option<&str>,
+    ) -> Option<String> {
+        let (kind, args) = match emit {
+            Emit::Asm => ("asm", " -Cllvm-args=-x86-asm-syntax=intel"),
+            Emit::LlvmIr => ("llvm-ir", ""),
+            Emit::Mir => ("mir", ""),
+        };
+        let opt = opt_level.map(|level| format!(" -Copt-level={}", level)).unwrap_or_default();
+        Some(format!(
+            "rustc --crate-type=lib --emit={}=-{}{} {}",
+            kind,
+            args,
+            opt,
+            src_path.to_str().unwrap()
+        ))
+    }
+
+    fn get_demangler(&self) -> Option<String> {
+        Some("c++filt".into())
+    }
+
+    fn get_formatter_command(&self) -> Option<String> {
+        Some("rustfmt --edition 2021".into())
+    }
+
+    fn get_linter_command(&self, src_path: &PathBuf) -> Option<String> {
+        Some(format!(
+            "clippy-driver --edition 2021 --error-format=short --emit=metadata -o /tmp/lint.rmeta -A dead_code -W clippy::all {}",
+            src_path.to_str().unwrap()
+        ))
+    }
+
     fn check_compiler_or_interpreter(&self) -> String {
         String::from("rustc --version")
     }
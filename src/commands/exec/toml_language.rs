@@ -0,0 +1,185 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::commands::exec::language::{get_default_logo_url, join_source_paths, Language};
+
+/// Directory of the languages defined by a TOML file instead of a `Language` implementation.
+pub const LANGUAGES_DIRECTORY: &str = "languages";
+
+/// A language defined by a file of `LANGUAGES_DIRECTORY`, e.g. `languages/lua.toml`.
+///
+/// Its commands are templates, in which `{src}` stands for the main source file, `{srcs}` for
+/// all the source files of the snippet and `{out}` for the compiled program, or the script of
+/// interpreted languages.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TomlLanguage {
+    name: String,
+    aliases: Vec<String>,
+    /// Defaults to `rustacean-<name>`, built from `images/Dockerfile.<name>`
+    image: Option<String>,
+    extension: String,
+    /// Prints the version of the compiler or interpreter
+    version: String,
+    compile: Option<String>,
+    #[serde(default = "default_run")]
+    run: String,
+    #[serde(default)]
+    allowed_flags: Vec<String>,
+    formatter: Option<String>,
+    linter: Option<String>,
+    logo: Option<String>,
+    wrap: Option<Wrap>,
+}
+
+/// Wraps snippets which do not match `unless`, e.g. in a main function.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Wrap {
+    unless: String,
+    /// Where `{code}` stands for the snippet
+    template: String,
+    #[serde(skip)]
+    pattern: Option<Regex>,
+}
+
+fn default_run() -> String {
+    "{out}".into()
+}
+
+impl TomlLanguage {
+    /// Reads and checks the definition of a language.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidData, format!("{}: {}", path.display(), msg));
+        let contents = fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let mut lang: TomlLanguage = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+
+        if lang.name.trim().is_empty() {
+            return Err(invalid("`name` cannot be empty".into()));
+        }
+        if lang.aliases.is_empty() {
+            return Err(invalid("`aliases` needs at least one code to run the language with".into()));
+        }
+        if let Some(alias) = lang.aliases.iter().find(|alias| {
+            alias.is_empty() || alias.chars().any(|c| c.is_whitespace() || c.is_ascii_uppercase())
+        }) {
+            return Err(invalid(format!("`{}` is not a valid alias, aliases are lowercase words", alias)));
+        }
+        if !lang.extension.starts_with('.') || lang.extension.len() < 2 {
+            return Err(invalid(format!("`{}` is not a file extension, e.g. `.lua`", lang.extension)));
+        }
+        if let Some(ref mut wrap) = lang.wrap {
+            wrap.pattern = Some(Regex::new(&wrap.unless).map_err(|e| invalid(format!("invalid `wrap.unless`: {}", e)))?);
+            if !wrap.template.contains("{code}") {
+                return Err(invalid("`wrap.template` does not contain `{code}`".into()));
+            }
+        }
+
+        Ok(lang)
+    }
+
+    pub fn get_aliases(&self) -> Vec<String> {
+        self.aliases.clone()
+    }
+
+    /// Fills in a command template, `{srcs}` leaving out the files of other extensions, like headers.
+    fn fill(&self, template: &str, src_paths: &[PathBuf], out_path: &Path) -> String {
+        let src = src_paths.first().and_then(|path| path.to_str()).unwrap_or("");
+        let srcs = if src_paths.is_empty() {
+            String::new()
+        } else {
+            join_source_paths(src_paths, &[self.extension.trim_start_matches('.')])
+        };
+        template
+            .replace("{srcs}", &srcs)
+            .replace("{src}", src)
+            .replace("{out}", out_path.to_str().unwrap())
+    }
+}
+
+/// Loads the languages defined in `directory`, in the order of their file names. A missing
+/// directory defines no language.
+pub fn load_toml_languages(directory: &Path) -> Vec<Result<TomlLanguage, Error>> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(directory) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    paths.sort();
+
+    paths.iter().map(|path| TomlLanguage::load(path)).collect()
+}
+
+impl Language for TomlLanguage {
+    fn get_image_name(&self) -> String {
+        match self.image {
+            Some(ref image) => image.clone(),
+            None => format!("rustacean-{}", self.name.to_lowercase()),
+        }
+    }
+
+    fn get_lang_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_source_file_ext(&self) -> String {
+        self.extension.clone()
+    }
+
+    fn pre_process_code(&self, code: &str, _src_path: &PathBuf) -> Option<String> {
+        let wrap = self.wrap.as_ref()?;
+        match wrap.pattern {
+            Some(ref pattern) if pattern.is_match(code) => None,
+            _ => Some(wrap.template.replace("{code}", code)),
+        }
+    }
+
+    fn get_compiler_command(&self, src_path: &PathBuf, exe_path: &PathBuf) -> Option<String> {
+        self.get_compiler_command_for_files(std::slice::from_ref(src_path), exe_path)
+    }
+
+    fn get_compiler_command_for_files(
+        &self,
+        src_paths: &[PathBuf],
+        exe_path: &PathBuf,
+    ) -> Option<String> {
+        self.compile
+            .as_ref()
+            .map(|compile| self.fill(compile, src_paths, exe_path))
+    }
+
+    fn get_execution_command(&self, path: &PathBuf) -> String {
+        self.fill(&self.run, &[], path)
+    }
+
+    fn get_allowed_flags(&self) -> Vec<String> {
+        self.allowed_flags.clone()
+    }
+
+    fn get_formatter_command(&self) -> Option<String> {
+        self.formatter.clone()
+    }
+
+    fn get_linter_command(&self, src_path: &PathBuf) -> Option<String> {
+        self.linter
+            .as_ref()
+            .map(|linter| self.fill(linter, std::slice::from_ref(src_path), Path::new("")))
+    }
+
+    fn check_compiler_or_interpreter(&self) -> String {
+        self.version.clone()
+    }
+
+    fn get_logo_url(&self) -> String {
+        match self.logo {
+            Some(ref logo) => logo.clone(),
+            None => get_default_logo_url(&self.name),
+        }
+    }
+}
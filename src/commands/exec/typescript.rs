@@ -30,6 +30,17 @@ impl Language for Typescript {
         format!("node {}.js", path.to_str().unwrap())
     }
 
+    fn get_formatter_command(&self) -> Option<String> {
+        Some("prettier --stdin-filepath main.ts".into())
+    }
+
+    fn get_linter_command(&self, src_path: &PathBuf) -> Option<String> {
+        Some(format!(
+            "eslint --no-eslintrc -c /usr/local/lib/eslintrc.json --resolve-plugins-relative-to /usr/local/lib -f unix {}",
+            src_path.to_str().unwrap()
+        ))
+    }
+
     fn check_compiler_or_interpreter(&self) -> String {
         String::from("tsc -v")
     }
@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+use std::io::{Error, ErrorKind};
+
+use crate::{
+    commands::exec::{
+        backend::ExecutionBackend, backend::Step, get_code_blocks, get_lang, pre_process_output, BoxedLang,
+    },
+    LangManager, Settings,
+};
+
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    http::AttachmentType,
+    model::{channel::Message, id::UserId},
+    prelude::Context,
+};
+
+/// Maximum length of a Discord message.
+const MESSAGE_LENGTH: usize = 2000;
+
+#[command]
+#[aliases("format")]
+#[description = "Formats a code snippet with the language's canonical formatter (rustfmt, clang-format, black, gofmt, prettier, ocamlformat). Your message needs to look like this:\r\n~fmt\r\n\\`\\`\\`language\r\n\r\ncode...\r\n\\`\\`\\`"]
+#[bucket = "exec_bucket"]
+fn fmt(ctx: &mut Context, msg: &Message, _args: Args) -> CommandResult {
+    let block = match get_code_blocks(&msg.content).into_iter().find(|block| !block.is_stdin()) {
+        Some(block) => block,
+        None => {
+            let _ = msg.reply(&ctx, "Please add a code section to your message, e.g.\n~fmt\n\\`\\`\\`rust\n**code**\n\\`\\`\\`")?;
+            return Ok(());
+        }
+    };
+
+    let (settings, lang, backend) = {
+        let data = ctx.data.read();
+        let settings = data.get::<Settings>().unwrap().lock().unwrap().clone();
+        let lang_manager = data.get::<LangManager>().unwrap().lock().unwrap();
+        (settings, get_lang(&lang_manager, &block.tag.to_ascii_lowercase()), lang_manager.get_backend())
+    };
+    let lang = match lang {
+        Ok(lang) => lang,
+        Err(e) => {
+            let _ = msg.reply(&ctx, format!(":x: {}", e))?;
+            return Ok(());
+        }
+    };
+
+    let code = match format_code(&settings, backend.as_ref(), &lang, &block.code) {
+        Ok(code) => code,
+        Err(e) => {
+            let _ = msg.reply(&ctx, format!(":x: {}", e))?;
+            return Ok(());
+        }
+    };
+
+    if let Some(reply) = format_reply(msg.author.id, &block.tag, &code) {
+        let _ = msg.channel_id.say(&ctx, reply)?;
+    } else {
+        let file = AttachmentType::Bytes {
+            data: Cow::Owned(code.into_bytes()),
+            filename: format!("formatted{}", lang.get_source_file_ext()),
+        };
+        let _ = msg.channel_id.send_files(&ctx.http, vec![file], |m| {
+            m.content(format!("<@{}>, here is the formatted code:", msg.author.id))
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Returns the reply showing the formatted `code` in a code section, or `None` when the code has
+/// to be attached instead: when it is too long for a message, when it would close the code
+/// section early or when it mentions everyone. The code is never altered.
+pub fn format_reply(author: UserId, tag: &str, code: &str) -> Option<String> {
+    let shown = format!("{}\n{}", tag, code);
+    if pre_process_output(shown.clone()) != shown {
+        return None;
+    }
+    let reply = format!("<@{}>,\n```{}```", author, shown);
+    if reply.len() > MESSAGE_LENGTH {
+        return None;
+    }
+
+    Some(reply)
+}
+
+/// Runs the formatter of `lang` on `code` in a new session, see `Language::get_formatter_command`.
+pub fn format_code(
+    settings: &Settings,
+    backend: &dyn ExecutionBackend,
+    lang: &BoxedLang,
+    code: &str,
+) -> Result<String, Error> {
+    let command = match lang.get_formatter_command() {
+        Some(command) => command,
+        None => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("There is no formatter for {}.", lang.get_lang_name()),
+            ))
+        }
+    };
+
+    info!("Formatting {} code", lang.get_lang_name());
+    let settings = &settings.for_language(&lang.get_lang_name());
+    let session = backend.start_session(settings, &lang.get_image_name())?;
+    let mut step = Step::new(&command, settings.compilation_timeout);
+    step.stdin = Some(code.to_string());
+    step.output_limit = settings.output_limit;
+    let res = backend.run_step(&session, &step);
+    backend.teardown(&session);
+    let res = res?;
+
+    if res.timed_out {
+        return Err(Error::other("The formatter timed out."));
+    }
+    if res.exit_code != Some(0) || res.is_truncated() {
+        let mut error = pre_process_output(res.stderr);
+        if error.len() > 1500 {
+            let mut end = 1500;
+            while !error.is_char_boundary(end) {
+                end -= 1;
+            }
+            error.truncate(end);
+        }
+        return Err(Error::other(format!(
+            "The formatter could not format your code:\n```\n{}```",
+            error
+        )));
+    }
+
+    Ok(res.stdout)
+}
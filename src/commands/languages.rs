@@ -1,4 +1,4 @@
-use crate::LangManager;
+use crate::{LangManager, Settings};
 
 use serenity::{
     framework::standard::{macros::command, CommandResult},
@@ -11,22 +11,25 @@ use serenity::{
 #[description = "Get a list of available programming languages for the `exec` command."]
 fn languages(ctx: &mut Context, msg: &Message) -> CommandResult {
     let data = ctx.data.read();
+    let settings = data.get::<Settings>().unwrap().lock().unwrap().clone();
     let lang_manager = data.get::<LangManager>().unwrap().lock().unwrap();
     let mut fields: Vec<(String, String, bool)> = Vec::new();
     for (lang_codes, boxed_lang) in lang_manager.get_languages() {
         if lang_manager.is_language_available(&(*boxed_lang)) {
-            fields.push((
-                boxed_lang.get_lang_name(),
-                format!(
-                    "({})",
-                    lang_codes
-                        .iter()
-                        .map(|code| format!("`{}`", code))
-                        .collect::<Vec<String>>()
-                        .join(", ")
-                ),
-                true,
-            ));
+            let mut value = format!(
+                "({})",
+                lang_codes
+                    .iter()
+                    .map(|code| format!("`{}`", code))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+            // Only the languages with limits of their own list them
+            if settings.languages.contains_key(&boxed_lang.get_lang_name().to_lowercase()) {
+                value.push('\n');
+                value.push_str(&format_limits(&settings.for_language(&boxed_lang.get_lang_name())));
+            }
+            fields.push((boxed_lang.get_lang_name(), value, true));
         }
     }
     fields.sort();
@@ -34,9 +37,26 @@ fn languages(ctx: &mut Context, msg: &Message) -> CommandResult {
     let _ = msg.channel_id.send_message(&ctx, |m| {
         m.embed(|e| {
             e.title("Languages")
-                .description("A list of available languages for the `exec` command.")
+                .description(format!(
+                    "A list of available languages for the `exec` command.\nLimits: {}",
+                    format_limits(&settings)
+                ))
                 .fields(fields)
         })
     })?;
     Ok(())
 }
+
+/// Describes the limits snippets run with, e.g. `0.80 CPU, 250m RAM, 30s to compile, 10s to run`.
+pub fn format_limits(settings: &Settings) -> String {
+    let mut limits = format!("{} CPU, {} RAM", settings.cpu_load, settings.ram_load);
+    if settings.pids_limit > 0 {
+        limits.push_str(&format!(", {} processes", settings.pids_limit));
+    }
+    limits.push_str(&format!(
+        ", {}s to compile, {}s to run",
+        settings.compilation_timeout, settings.execution_timeout
+    ));
+
+    limits
+}
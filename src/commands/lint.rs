@@ -0,0 +1,262 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    commands::exec::{backend::ExecutionBackend, backend::Step, get_code_blocks, get_lang, save_code, BoxedLang},
+    LangManager, Settings,
+};
+
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::{channel::Message, id::UserId},
+    prelude::Context,
+    utils::Colour,
+};
+
+/// Maximum length of the list of diagnostics in the result embed.
+const DIAGNOSTICS_LENGTH: usize = 1900;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    fn get_emoji(&self) -> &'static str {
+        match self {
+            Severity::Error => ":x:",
+            Severity::Warning => ":warning:",
+            Severity::Note => ":information_source:",
+        }
+    }
+}
+
+/// A problem reported by a linter, at a line of the snippet.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub column: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[command]
+#[description = "Checks a code snippet with the language's linter (clippy, shellcheck, ruff, eslint, or gcc's warnings for C and C++) and lists the problems it finds. Your message needs to look like this:\r\n~lint\r\n\\`\\`\\`language\r\n\r\ncode...\r\n\\`\\`\\`"]
+#[bucket = "exec_bucket"]
+fn lint(ctx: &mut Context, msg: &Message, _args: Args) -> CommandResult {
+    let block = match get_code_blocks(&msg.content).into_iter().find(|block| !block.is_stdin()) {
+        Some(block) => block,
+        None => {
+            let _ = msg.reply(&ctx, "Please add a code section to your message, e.g.\n~lint\n\\`\\`\\`rust\n**code**\n\\`\\`\\`")?;
+            return Ok(());
+        }
+    };
+
+    let (settings, lang, backend) = {
+        let data = ctx.data.read();
+        let settings = data.get::<Settings>().unwrap().lock().unwrap().clone();
+        let lang_manager = data.get::<LangManager>().unwrap().lock().unwrap();
+        (settings, get_lang(&lang_manager, &block.tag.to_ascii_lowercase()), lang_manager.get_backend())
+    };
+    let lang = match lang {
+        Ok(lang) => lang,
+        Err(e) => {
+            let _ = msg.reply(&ctx, format!(":x: {}", e))?;
+            return Ok(());
+        }
+    };
+
+    let diagnostics = match lint_code(&settings, backend.as_ref(), &lang, &block.code, msg.author.id) {
+        Ok(diagnostics) => diagnostics,
+        Err(e) => {
+            let _ = msg.reply(&ctx, format!(":x: {}", e))?;
+            return Ok(());
+        }
+    };
+
+    let count = |severity| diagnostics.iter().filter(|d| d.severity == severity).count();
+    let (errors, warnings, notes) = (count(Severity::Error), count(Severity::Warning), count(Severity::Note));
+    let colour = if errors > 0 {
+        Colour::RED
+    } else if warnings > 0 {
+        Colour::ORANGE
+    } else {
+        Colour::DARK_GREEN
+    };
+    let summary = if diagnostics.is_empty() {
+        String::from(":white_check_mark: No problems found")
+    } else {
+        format!("{} errors, {} warnings, {} notes", errors, warnings, notes)
+    };
+
+    let _ = msg.channel_id.send_message(&ctx, |m| {
+        m.content(format!("<@{}>,", msg.author.id)).embed(|e| {
+            e.title(format!("{} lint", lang.get_lang_name()))
+                .description(format!("{}\n\n{}", summary, format_diagnostics(&diagnostics)))
+                .colour(colour)
+                .thumbnail(lang.get_logo_url())
+                .author(|a| a.name(&msg.author.name).icon_url(msg.author.face()))
+        })
+    })?;
+
+    Ok(())
+}
+
+/// Runs the linter of `lang` on `code` in a new session, see `Language::get_linter_command`.
+pub fn lint_code(
+    settings: &Settings,
+    backend: &dyn ExecutionBackend,
+    lang: &BoxedLang,
+    code: &str,
+    author: UserId,
+) -> Result<Vec<Diagnostic>, Error> {
+    let file_name = format!("main{}", lang.get_source_file_ext());
+    let path_in_container = PathBuf::from("/home").join(&file_name);
+    let command = match lang.get_linter_command(&path_in_container) {
+        Some(command) => command,
+        None => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("There is no linter for {}.", lang.get_lang_name()),
+            ))
+        }
+    };
+
+    // Snippets are wrapped like when they are run, the lines of the wrapper are left out
+    let (code, offset) = match lang.pre_process_code(code, &path_in_container) {
+        Some(wrapped) => match wrapped.find(code) {
+            Some(start) => {
+                let offset = wrapped[..start].matches('\n').count();
+                (wrapped, offset)
+            }
+            None => (wrapped, 0),
+        },
+        None => (code.to_string(), 0),
+    };
+    let lines = code.lines().count().saturating_sub(offset);
+
+    info!("Linting {} code", lang.get_lang_name());
+    let src_path = save_code(&code, author, &lang.get_source_file_ext())?;
+    let settings = &settings.for_language(&lang.get_lang_name());
+    let session = match backend.start_session(settings, &lang.get_image_name()) {
+        Ok(session) => session,
+        Err(e) => {
+            let _ = fs::remove_file(&src_path);
+            return Err(e);
+        }
+    };
+    let mut step = Step::new(&command, settings.compilation_timeout);
+    step.output_limit = settings.output_limit;
+    let res = backend
+        .copy_file(&session, &src_path, &path_in_container)
+        .and_then(|_| backend.run_step(&session, &step));
+    backend.teardown(&session);
+    let _ = fs::remove_file(&src_path);
+    let res = res?;
+
+    if res.timed_out {
+        return Err(Error::other("The linter timed out."));
+    }
+    let output = format!("{}\n{}", res.stdout, res.stderr);
+    let diagnostics: Vec<Diagnostic> = parse_diagnostics(&output, &file_name)
+        .into_iter()
+        .filter(|d| d.line > offset && d.line <= offset + lines)
+        .map(|d| Diagnostic {
+            line: d.line - offset,
+            ..d
+        })
+        .collect();
+    if diagnostics.is_empty() && res.exit_code != Some(0) && !res.stderr.trim().is_empty() {
+        let mut error = res.stderr.replace("```", "");
+        if error.len() > 1500 {
+            let mut end = 1500;
+            while !error.is_char_boundary(end) {
+                end -= 1;
+            }
+            error.truncate(end);
+        }
+        return Err(Error::other(format!(
+            "The linter could not check your code:\n```\n{}```",
+            error
+        )));
+    }
+
+    Ok(diagnostics)
+}
+
+/// Parses the diagnostics about `file_name` written by a linter, one per line as
+/// `path:line:column: severity: message`. The severity may also come last like ESLint
+/// writes it, `[Error/rule]`, otherwise the diagnostic is taken as a warning.
+pub fn parse_diagnostics(output: &str, file_name: &str) -> Vec<Diagnostic> {
+    use regex::Regex;
+
+    let location = Regex::new(r"^(?P<path>[^\s:]+):(?P<line>\d+):(?:(?P<column>\d+):)? (?P<rest>.+)$").unwrap();
+    let leading = Regex::new(r"^(?P<severity>fatal error|error|warning|note|info|style|help)(?:\[[^\]]*\])?: (?P<message>.+)$").unwrap();
+    let trailing = Regex::new(r"^(?P<message>.+) \[(?P<severity>Error|Warning)/(?P<rule>[^\]]+)\]$").unwrap();
+
+    let mut diagnostics = Vec::new();
+    for line in output.lines() {
+        // Linters may also report problems in headers or configuration files
+        let caps = match location.captures(line.trim_end()) {
+            Some(caps) if Path::new(&caps["path"]).file_name() == Some(OsStr::new(file_name)) => caps,
+            _ => continue,
+        };
+        let rest = &caps["rest"];
+        let (severity, message) = if let Some(parts) = leading.captures(rest) {
+            let severity = match &parts["severity"] {
+                "fatal error" | "error" => Severity::Error,
+                "warning" => Severity::Warning,
+                _ => Severity::Note,
+            };
+            (severity, parts["message"].to_string())
+        } else if let Some(parts) = trailing.captures(rest) {
+            let severity = match &parts["severity"] {
+                "Error" => Severity::Error,
+                _ => Severity::Warning,
+            };
+            (severity, format!("{} ({})", &parts["message"], &parts["rule"]))
+        } else {
+            (Severity::Warning, rest.to_string())
+        };
+
+        let diagnostic = Diagnostic {
+            line: caps["line"].parse().unwrap_or(0),
+            column: caps.name("column").and_then(|column| column.as_str().parse().ok()),
+            severity,
+            message,
+        };
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    diagnostics
+}
+
+/// Lists diagnostics for the result embed, as many as fit in it.
+pub fn format_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    let mut list = String::new();
+    for (i, diagnostic) in diagnostics.iter().enumerate() {
+        let location = match diagnostic.column {
+            Some(column) => format!("{}:{}", diagnostic.line, column),
+            None => diagnostic.line.to_string(),
+        };
+        let entry = format!(
+            "{} `{}` {}\n",
+            diagnostic.severity.get_emoji(),
+            location,
+            diagnostic.message.replace('`', "'")
+        );
+        if list.len() + entry.len() > DIAGNOSTICS_LENGTH {
+            list.push_str(&format!("... and {} more", diagnostics.len() - i));
+            break;
+        }
+        list.push_str(&entry);
+    }
+
+    list
+}
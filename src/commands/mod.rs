@@ -8,6 +8,11 @@ pub mod invite;
 pub mod support;
 pub mod versions;
 pub mod stats;
+pub mod pool;
+pub mod packages;
+pub mod fmt;
+pub mod lint;
+pub mod reload;
 
 pub use exec::*;
 pub use git::*;
@@ -19,3 +24,8 @@ pub use invite::*;
 pub use support::*;
 pub use versions::*;
 pub use stats::*;
+pub use pool::*;
+pub use packages::*;
+pub use fmt::*;
+pub use lint::*;
+pub use reload::*;
@@ -0,0 +1,60 @@
+use crate::{commands::exec::get_lang, LangManager};
+
+use serenity::{
+    framework::standard::{macros::command, Args, CommandResult},
+    model::channel::Message,
+    prelude::Context,
+};
+
+#[command]
+#[aliases("package", "pkgs", "crates")]
+#[description = "Lists the third-party packages that `exec` snippets can use for a language."]
+#[example = "rust"]
+fn packages(ctx: &mut Context, msg: &Message, mut args: Args) -> CommandResult {
+    let lang_code = match args.single::<String>() {
+        Ok(lang_code) => lang_code.to_ascii_lowercase(),
+        Err(_) => {
+            let _ = msg.reply(&ctx, "Please specify a language, e.g. `packages rust`.")?;
+            return Ok(());
+        }
+    };
+    let lang = {
+        let data = ctx.data.read();
+        let lang_manager = data.get::<LangManager>().unwrap().lock().unwrap();
+        get_lang(&lang_manager, &lang_code)
+    };
+    let lang = match lang {
+        Ok(lang) => lang,
+        Err(e) => {
+            let _ = msg.reply(&ctx, format!(":x: {}", e))?;
+            return Ok(());
+        }
+    };
+
+    let packages = lang.get_packages();
+    if packages.is_empty() {
+        let _ = msg.reply(
+            &ctx,
+            format!("No packages are available for {}.", lang.get_lang_name()),
+        )?;
+        return Ok(());
+    }
+
+    let list = packages
+        .iter()
+        .map(|(name, version)| format!("`{}` {}", name, version))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let _ = msg.channel_id.send_message(&ctx, |m| {
+        m.embed(|e| {
+            e.title(format!("{} packages", lang.get_lang_name()))
+                .description(format!(
+                    "These packages are installed in the {} image and can be used in `exec` snippets.\n\n{}",
+                    lang.get_lang_name(),
+                    list
+                ))
+                .thumbnail(lang.get_logo_url())
+        })
+    })?;
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::LangManager;
+
+use serenity::{
+    prelude::Context,
+    model::channel::Message,
+    framework::standard::{ CommandResult, macros::command },
+};
+
+#[command]
+#[aliases("pools")]
+#[description = "Shows the state of the warm container pools (idle/size, containers being started, executions served from the pool)."]
+#[owners_only]
+fn pool(ctx: &mut Context, msg: &Message) -> CommandResult {
+    let data = ctx.data.read();
+    let lang_manager = data.get::<LangManager>().unwrap().lock().unwrap();
+    let status = match lang_manager.get_pool() {
+        Some(pool) => pool.status(),
+        None => {
+            let _ = msg.reply(&ctx, "Container pools are disabled. Set `pool_size` in the configuration file to enable them.")?;
+            return Ok(());
+        }
+    };
+
+    let mut fields: Vec<(String, String, bool)> = Vec::new();
+    for boxed_lang in lang_manager.get_languages().values() {
+        if let Some(status) = status.get(&boxed_lang.get_image_name()) {
+            fields.push((
+                boxed_lang.get_lang_name(),
+                format!(
+                    "{}/{} idle, {} starting\n{} hits, {} misses",
+                    status.idle, status.size, status.starting, status.hits, status.misses
+                ),
+                true
+            ));
+        }
+    }
+    fields.sort();
+
+    let _ = msg.channel_id.send_message(&ctx, |m| m
+        .embed(|e| e
+            .title("Container pools")
+            .description("Pre-started containers waiting for an execution.")
+            .fields(fields)
+        )
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,151 @@
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Mutex};
+
+use serenity::{
+    prelude::{ Context, RwLock, ShareMap },
+    model::channel::Message,
+    framework::standard::{ CommandResult, macros::command },
+};
+
+use crate::{LangManager, Settings};
+
+/// Held while a reload is in progress, so that reloads do not overlap.
+static RELOADING: Mutex<()> = Mutex::new(());
+
+#[command]
+#[description = "Reloads the configuration file and the language definitions. Only the languages which changed are checked again, executions in progress are not interrupted."]
+#[owners_only]
+fn reload(ctx: &mut Context, msg: &Message) -> CommandResult {
+    let _ = msg.channel_id.broadcast_typing(&ctx);
+    match reload_configuration(&ctx.data) {
+        Ok(report) => {
+            let _ = msg.reply(&ctx, report)?;
+        }
+        Err(e) => {
+            let _ = msg.reply(&ctx, format!("The configuration was not reloaded: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Re-reads the configuration and the languages, then swaps the new `Settings` and
+/// `LangManager` in at once, while the languages which changed are checked in the background.
+/// Executions in progress keep the settings and the backend they started with.
+///
+/// Returns a summary of the reload for the bot owner.
+pub fn reload_configuration(data: &Arc<RwLock<ShareMap>>) -> Result<String, Error> {
+    let _reloading = match RELOADING.try_lock() {
+        Ok(guard) => guard,
+        // A previous reload panicked, e.g. while checking languages, and is over
+        Err(std::sync::TryLockError::Poisoned(e)) => e.into_inner(),
+        Err(std::sync::TryLockError::WouldBlock) => {
+            return Err(Error::new(ErrorKind::WouldBlock, "a reload is already in progress"))
+        }
+    };
+    info!("Reloading the configuration");
+
+    let mut settings = crate::read_settings()?;
+    let mut lang_manager = LangManager::new();
+    lang_manager.resolve_language_limits(&mut settings)?;
+
+    let (settings_lock, lang_manager_lock) = {
+        let data = data.read();
+        (
+            data.get::<Settings>().unwrap().clone(),
+            data.get::<LangManager>().unwrap().clone(),
+        )
+    };
+    let (restart_required, previous_langs, changed) = {
+        let previous_settings = settings_lock.lock().unwrap();
+        let previous = lang_manager_lock.lock().unwrap();
+        (
+            get_restart_required(&previous_settings, &settings),
+            get_language_names(&previous),
+            lang_manager.take_checked_state(&previous),
+        )
+    };
+    // The languages which changed become available again once their image is built
+    lang_manager.check_languages(&changed, settings.build_concurrency);
+    lang_manager.init_pool(&settings);
+
+    let new_langs = get_language_names(&lang_manager);
+
+    let previous = {
+        // Both are swapped under the write lock, so that no one sees one without the other
+        let _data = data.write();
+        *settings_lock.lock().unwrap() = settings;
+        std::mem::replace(&mut *lang_manager_lock.lock().unwrap(), lang_manager)
+    };
+    if let Some(pool) = previous.get_pool() {
+        pool.drain();
+    }
+
+    let added: Vec<&String> = new_langs.iter().filter(|name| !previous_langs.contains(name)).collect();
+    let removed: Vec<&String> = previous_langs.iter().filter(|name| !new_langs.contains(name)).collect();
+    let rechecked: Vec<&String> = changed.iter().filter(|name| !added.contains(name)).collect();
+
+    let mut report = String::from("Configuration reloaded.");
+    for (title, names) in [("Added", &added), ("Removed", &removed), ("Being checked again", &rechecked)].iter() {
+        if !names.is_empty() {
+            report.push_str(&format!("\n{}: {}", title, join(names)));
+        }
+    }
+    if !changed.is_empty() {
+        report.push_str("\nNew and changed languages are available once their image is built.");
+    }
+    if !restart_required.is_empty() {
+        report.push_str(&format!(
+            "\nChanges to {} will only apply after a restart.",
+            restart_required.join(", ")
+        ));
+    }
+    info!("{}", report.replace('\n', " "));
+
+    Ok(report)
+}
+
+/// Returns the settings which changed but are only read when the bot starts.
+fn get_restart_required(previous: &Settings, settings: &Settings) -> Vec<&'static str> {
+    let mut names = Vec::new();
+    if previous.discord_token != settings.discord_token {
+        names.push("discord_token");
+    }
+    if previous.command_prefix != settings.command_prefix {
+        names.push("command_prefix");
+    }
+    if previous.bot_owners != settings.bot_owners {
+        names.push("bot_owners");
+    }
+    if previous.log_level_term != settings.log_level_term
+        || previous.log_level_file != settings.log_level_file
+        || previous.log_file != settings.log_file
+    {
+        names.push("logging");
+    }
+    if previous.db_connection_pool_size != settings.db_connection_pool_size {
+        names.push("db_connection_pool_size");
+    }
+    if previous.webhook_id != settings.webhook_id
+        || previous.webhook_token != settings.webhook_token
+        || previous.webhook_frequency != settings.webhook_frequency
+        || previous.webhook_role != settings.webhook_role
+    {
+        names.push("webhooks");
+    }
+    names
+}
+
+fn get_language_names(lang_manager: &LangManager) -> Vec<String> {
+    let mut names: Vec<String> = lang_manager
+        .get_languages()
+        .values()
+        .map(|lang| lang.get_lang_name())
+        .collect();
+    names.sort();
+    names
+}
+
+fn join(names: &[&String]) -> String {
+    names.iter().map(|name| name.as_str()).collect::<Vec<&str>>().join(", ")
+}
@@ -1,15 +1,38 @@
 use duct::cmd;
-use std::collections::HashMap;
+use sha1::{Digest, Sha1};
+use std::collections::{HashMap, HashSet};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
 use typemap::Key;
 
+use crate::commands::exec::backend::{BoxedBackend, ContainerPool, DockerBackend};
 use crate::commands::exec::language::Language;
 use crate::commands::exec::*;
+use crate::{LanguageLimits, Settings};
+
+/// Label of the language images, holding the hash of the Dockerfile and of the files it copies
+/// they were built from, see `hash_build_inputs`.
+const BUILD_HASH_LABEL: &str = "rustacean.build-sha1";
 
 pub struct LangManager {
     languages: HashMap<Vec<String>, Arc<Box<dyn Language + Sync + Send>>>,
+    checks: Arc<Mutex<Checks>>,
+    backend: BoxedBackend,
+}
+
+/// What is known of the languages, shared with the threads building their images.
+#[derive(Default)]
+struct Checks {
     availability: HashMap<String, bool>,
     versions: HashMap<String, Option<String>>,
+    /// The `fingerprint` of each language when it was last checked
+    fingerprints: HashMap<String, u64>,
+    /// Languages whose image is being built
+    building: HashSet<String>,
+    /// Told when the image of a language being built is ready
+    pool: Option<Arc<ContainerPool>>,
 }
 
 impl Key for LangManager {
@@ -26,8 +49,8 @@ impl LangManager {
     pub fn new() -> LangManager {
         let mut mngr = LangManager {
             languages: HashMap::new(),
-            availability: HashMap::new(),
-            versions: HashMap::new(),
+            checks: Arc::new(Mutex::new(Checks::default())),
+            backend: Arc::new(DockerBackend::default()),
         };
 
         mngr.languages
@@ -36,18 +59,10 @@ impl LangManager {
             .insert(vec!["c".into()], Arc::new(Box::new(C)));
         mngr.languages
             .insert(vec!["cpp".into()], Arc::new(Box::new(Cpp)));
-        mngr.languages
-            .insert(vec!["php".into()], Arc::new(Box::new(Php)));
-        mngr.languages
-            .insert(vec!["lua".into()], Arc::new(Box::new(Lua)));
         mngr.languages.insert(
             vec!["asmx86".into(), "asm_x86".into()],
             Arc::new(Box::new(Asmx86)),
         );
-        mngr.languages.insert(
-            vec!["hs".into(), "haskell".into()],
-            Arc::new(Box::new(Haskell)),
-        );
         mngr.languages.insert(
             vec![
                 "asmx64".into(),
@@ -61,14 +76,10 @@ impl LangManager {
             vec!["kt".into(), "kotlin".into()],
             Arc::new(Box::new(Kotlin)),
         );
-        mngr.languages
-            .insert(vec!["sh".into(), "shell".into()], Arc::new(Box::new(Shell)));
         mngr.languages.insert(
             vec!["py".into(), "python".into()],
             Arc::new(Box::new(Python)),
         );
-        mngr.languages
-            .insert(vec!["rb".into(), "ruby".into()], Arc::new(Box::new(Ruby)));
         mngr.languages.insert(
             vec!["js".into(), "javascript".into()],
             Arc::new(Box::new(JavaScript)),
@@ -85,8 +96,6 @@ impl LangManager {
             .insert(vec!["vb".into(), "vbnet".into()], Arc::new(Box::new(Vb)));
         mngr.languages
             .insert(vec!["java".into()], Arc::new(Box::new(Java)));
-        mngr.languages
-            .insert(vec!["julia".into()], Arc::new(Box::new(Julia)));
         mngr.languages
             .insert(vec!["go".into()], Arc::new(Box::new(Go)));
         mngr.languages
@@ -100,9 +109,39 @@ impl LangManager {
 	            "pony".into(),
 	            "ponyc".into(),
 	            "ponylang".into()], Arc::new(Box::new(Pony)));
+
+        mngr.load_toml_languages(std::path::Path::new(LANGUAGES_DIRECTORY));
         mngr
     }
 
+    /// Adds the languages defined in `directory`, see `TomlLanguage`. A language reusing the
+    /// name or an alias of another one is left out.
+    pub fn load_toml_languages(&mut self, directory: &std::path::Path) {
+        for lang in load_toml_languages(directory) {
+            let lang = match lang {
+                Ok(lang) => lang,
+                Err(e) => {
+                    error!("Could not load a language: {}", e);
+                    continue;
+                }
+            };
+            let aliases = lang.get_aliases();
+            let conflict = self.languages.iter().find(|(codes, other)| {
+                other.get_lang_name().eq_ignore_ascii_case(&lang.get_lang_name())
+                    || codes.iter().any(|code| aliases.contains(code))
+            });
+            if let Some((_, other)) = conflict {
+                error!(
+                    "Could not load the language {}: its name or one of its aliases is already used by {}",
+                    lang.get_lang_name(),
+                    other.get_lang_name()
+                );
+                continue;
+            }
+            self.languages.insert(aliases, Arc::new(Box::new(lang)));
+        }
+    }
+
     pub fn get(&self, lang: &str) -> Option<Arc<Box<dyn Language + Sync + Send>>> {
         for (lang_codes, boxed_lang) in self.languages.iter() {
             for l in lang_codes {
@@ -115,14 +154,19 @@ impl LangManager {
     }
 
     pub fn is_language_available(&self, lang: &Box<dyn Language + Sync + Send>) -> bool {
-        match self.availability.get(&lang.get_lang_name()) {
+        match self.checks.lock().unwrap().availability.get(&lang.get_lang_name()) {
             Some(availability) => *availability,
             None => false,
         }
     }
 
+    /// Whether the image of `lang` is being built, after which it may become available.
+    pub fn is_language_building(&self, lang: &Arc<Box<dyn Language + Sync + Send>>) -> bool {
+        self.checks.lock().unwrap().building.contains(&lang.get_lang_name())
+    }
+
     pub fn get_language_version(&self, lang: &Box<dyn Language + Sync + Send>) -> Option<String> {
-        match self.versions.get(&lang.get_lang_name()) {
+        match self.checks.lock().unwrap().versions.get(&lang.get_lang_name()) {
             Some(versions) => versions.clone(),
             None => {
                 error!("Language {} does not exist", &lang.get_lang_name());
@@ -148,104 +192,464 @@ impl LangManager {
         &self.languages
     }
 
-    pub fn check_languages_versions(&mut self) {
-        info!("Checking languages versions");
-        let mut results: Vec<(bool, String)> = Vec::new();
+    /// Builds the images of the languages named `lang_names` in the background, `concurrency`
+    /// of them at a time, and checks their versions. Each language becomes available as soon
+    /// as its image is ready, and its pool is filled then.
+    ///
+    /// Images are only rebuilt when their Dockerfile changed since they were built. The
+    /// returned thread ends once every language is checked.
+    pub fn check_languages(&self, lang_names: &[String], concurrency: usize) -> JoinHandle<()> {
+        let queue: Vec<Arc<Box<dyn Language + Sync + Send>>> = self
+            .languages
+            .values()
+            .filter(|boxed_lang| lang_names.contains(&boxed_lang.get_lang_name()))
+            .cloned()
+            .collect();
+        {
+            let mut checks = self.checks.lock().unwrap();
+            for boxed_lang in queue.iter() {
+                let lang_name = boxed_lang.get_lang_name();
+                checks.availability.insert(lang_name.clone(), false);
+                checks.versions.insert(lang_name.clone(), None);
+                checks.fingerprints.insert(lang_name.clone(), fingerprint(boxed_lang));
+                checks.building.insert(lang_name);
+            }
+        }
+
+        info!("Checking {} languages in the background", queue.len());
+        let queue = Arc::new(Mutex::new(queue));
+        let checks = self.checks.clone();
+        std::thread::spawn(move || {
+            let workers: Vec<JoinHandle<()>> = (0..concurrency.max(1))
+                .map(|_| {
+                    let queue = queue.clone();
+                    let checks = checks.clone();
+                    std::thread::spawn(move || loop {
+                        let boxed_lang = match queue.lock().unwrap().pop() {
+                            Some(boxed_lang) => boxed_lang,
+                            None => break,
+                        };
+                        check_language(&boxed_lang, &checks);
+                    })
+                })
+                .collect();
+            for worker in workers {
+                let _ = worker.join();
+            }
 
+            for args in [["image", "prune", "-f"], ["container", "prune", "-f"]].iter() {
+                if let Err(e) = duct::cmd("docker", args).stdout_null().run() {
+                    error!("Could not run docker {}: {}", args.join(" "), e);
+                }
+            }
+            info!("Languages checked");
+        })
+    }
+
+    /// Takes the availability and version of the languages which did not change since they
+    /// were checked by `previous`, as well as its backend, and returns the names of the other
+    /// languages, which are left to `check_languages`.
+    ///
+    /// A language changed when it is new, or when its image name, its version command or its
+    /// Dockerfile is not the same anymore. Languages still being built by `previous` are
+    /// checked again as well.
+    pub fn take_checked_state(&mut self, previous: &LangManager) -> Vec<String> {
+        let previous_checks = previous.checks.lock().unwrap();
+        self.backend = match previous_checks.pool {
+            Some(ref pool) => pool.get_backend(),
+            None => previous.backend.clone(),
+        };
+
+        let mut checks = self.checks.lock().unwrap();
+        let mut changed = Vec::new();
         for boxed_lang in self.languages.values() {
             let lang_name = boxed_lang.get_lang_name();
-            let low_lang_name = lang_name.to_lowercase();
-            self.versions.insert(lang_name.clone(), None);
-            match cmd!(
-                "docker",
-                "run",
-                "-t",
-                format!("rustacean-{}", low_lang_name),
-                "/bin/bash",
-                "-c",
-                boxed_lang.check_compiler_or_interpreter()
-            )
-            .stdout_capture()
-            .run()
+            let fingerprint = fingerprint(boxed_lang);
+            if previous_checks.fingerprints.get(&lang_name) != Some(&fingerprint)
+                || previous_checks.building.contains(&lang_name)
             {
-                Ok(res) => {
-                    if res.status.success() {
-                        let mut output = String::from(std::str::from_utf8(&res.stdout).unwrap());
-                        output.truncate(50);
-                        results.push((true, format!("    - {}: {}", &lang_name, output)));
-                        self.versions.insert(lang_name, Some(output));
-                    } else {
-                        results.push((false, format!("    - {}: Unavailable", &lang_name)));
-                    }
-                }
-                Err(e) => {
-                    results.push((false, format!("    - {}: Unavailable ({})", &lang_name, e)));
-                }
-            };
-        }
-
-        for (is_info, msg) in results {
-            if is_info {
-                info!("{}", msg);
-            } else {
-                warn!("{}", msg);
+                changed.push(lang_name);
+                continue;
+            }
+            checks.fingerprints.insert(lang_name.clone(), fingerprint);
+            if let Some(availability) = previous_checks.availability.get(&lang_name) {
+                checks.availability.insert(lang_name.clone(), *availability);
+            }
+            if let Some(version) = previous_checks.versions.get(&lang_name) {
+                checks.versions.insert(lang_name, version.clone());
             }
         }
+        changed.sort();
 
-        match cmd!("docker", "container", "prune", "-f").run() {
-            Ok(_) => {}
-            Err(e) => panic!(e),
-        };
+        changed
     }
 
-    pub fn check_available_languages(&mut self) {
-        info!("Checking available languages...");
-        let mut results: Vec<(bool, String)> = Vec::new();
+    pub fn get_language_names(&self) -> Vec<String> {
+        self.languages.values().map(|lang| lang.get_lang_name()).collect()
+    }
 
-        for boxed_lang in self.languages.values() {
+    pub fn get_backend(&self) -> BoxedBackend {
+        self.backend.clone()
+    }
+
+    pub fn set_backend(&mut self, backend: BoxedBackend) {
+        self.backend = backend;
+    }
+
+    pub fn get_pool(&self) -> Option<Arc<ContainerPool>> {
+        self.checks.lock().unwrap().pool.clone()
+    }
+
+    /// Puts a `ContainerPool` in front of the backend, sized from `pool_size` and
+    /// `pool_sizes` for every available language, whose sessions get the limits of their language.
+    /// The pools of the languages being built are filled once their image is ready.
+    pub fn init_pool(&mut self, settings: &Settings) {
+        let previous = self.checks.lock().unwrap().pool.take();
+        if let Some(pool) = previous {
+            pool.drain();
+            self.backend = pool.get_backend();
+        }
+
+        // Held until the pool is set, for languages not to become available unnoticed meanwhile
+        let mut checks = self.checks.lock().unwrap();
+        let mut sizes = HashMap::new();
+        let mut lang_settings = Vec::new();
+        let mut waiting = Vec::new();
+        for (lang_codes, boxed_lang) in self.languages.iter() {
             let lang_name = boxed_lang.get_lang_name();
-            let low_lang_name = lang_name.to_lowercase();
-            self.availability.insert(lang_name.clone(), false);
-            match cmd!(
-                "docker",
-                "build",
-                "-t",
-                format!("rustacean-{}", low_lang_name),
-                "-f",
-                format!("images/Dockerfile.{}", low_lang_name),
-                "."
-            )
-            .run()
-            {
-                Ok(res) => {
-                    if res.status.success() {
-                        results.push((true, format!("    - {}: Available", &lang_name)));
-                        self.availability.insert(lang_name, true);
-                    } else {
-                        results.push((false, format!("    - {}: Unavailable", &lang_name)));
-                    }
+            let building = checks.building.contains(&lang_name);
+            if !building && checks.availability.get(&lang_name) != Some(&true) {
+                continue;
+            }
+            let lang_name = boxed_lang.get_lang_name().to_lowercase();
+            let size = lang_codes
+                .iter()
+                .chain(std::iter::once(&lang_name))
+                .find_map(|code| settings.pool_sizes.get(code))
+                .copied()
+                .unwrap_or(settings.pool_size);
+            if size > 0 {
+                sizes.insert(boxed_lang.get_image_name(), size);
+                if building {
+                    waiting.push(boxed_lang.get_image_name());
                 }
-                Err(e) => {
-                    results.push((false, format!("    - {}: Unavailable ({})", &lang_name, e)));
+                if settings.languages.contains_key(&lang_name) {
+                    lang_settings.push((boxed_lang.get_image_name(), settings.for_language(&lang_name)));
                 }
-            };
+            }
+        }
+        if sizes.is_empty() {
+            return;
         }
 
-        for (is_info, msg) in results {
-            if is_info {
-                info!("{}", msg);
-            } else {
-                warn!("{}", msg);
+        info!("Starting container pools");
+        let pool = Arc::new(ContainerPool::new(self.backend.clone(), settings.clone(), sizes));
+        for (image, settings) in lang_settings {
+            pool.set_settings(&image, settings);
+        }
+        for image in waiting {
+            pool.wait_for_image(&image);
+        }
+        pool.fill();
+        self.backend = pool.clone();
+        checks.pool = Some(pool);
+    }
+
+    /// Checks the `[languages.<name>]` tables of `settings` and keys them by lowercase language
+    /// name, so that `Settings::for_language` finds them whichever alias they were given by.
+    pub fn resolve_language_limits(&self, settings: &mut Settings) -> Result<(), Error> {
+        let invalid = |msg: String| Error::new(ErrorKind::InvalidInput, msg);
+        check_limits("", &Some(settings.cpu_load.clone()), &Some(settings.ram_load.clone()), &Some(settings.kernel_memory.clone()))
+            .map_err(invalid)?;
+
+        let mut resolved: HashMap<String, LanguageLimits> = HashMap::new();
+        let mut tables: Vec<(&String, &LanguageLimits)> = settings.languages.iter().collect();
+        tables.sort_by_key(|(code, _)| code.as_str());
+        for (code, limits) in tables {
+            let lang_name = match self.get(&code.to_lowercase()) {
+                Some(lang) => lang.get_lang_name().to_lowercase(),
+                None => {
+                    // Languages are not known to be available yet, all of them are suggested
+                    let suggestion = find_closest_code(code, self.languages.keys().flatten());
+                    return Err(invalid(match suggestion {
+                        Some(suggestion) => format!("unknown language in [languages.{}], did you mean `{}`?", code, suggestion),
+                        None => format!("unknown language in [languages.{}]", code),
+                    }));
+                }
+            };
+            check_limits(&format!("[languages.{}] ", code), &limits.cpu_load, &limits.ram_load, &limits.kernel_memory)
+                .map_err(invalid)?;
+            if resolved.insert(lang_name.clone(), limits.clone()).is_some() {
+                return Err(invalid(format!(
+                    "the limits of {} are set twice, [languages.{}] is one of its aliases",
+                    lang_name, code
+                )));
             }
         }
+        settings.languages = resolved;
 
-        match cmd!("docker", "image", "prune", "-f").run() {
-            Ok(_) => {}
-            Err(e) => panic!(e),
-        };
+        Ok(())
     }
 
     pub fn set_language_available(&mut self, lang: String, availability: bool) {
-        self.availability.insert(lang, availability);
+        let mut checks = self.checks.lock().unwrap();
+        if let Some(boxed_lang) = self.languages.values().find(|boxed_lang| boxed_lang.get_lang_name() == lang) {
+            checks.fingerprints.insert(lang.clone(), fingerprint(boxed_lang));
+        }
+        checks.availability.insert(lang, availability);
+    }
+}
+
+/// Builds the image of `lang`, then checks its version, reporting both to `checks`.
+fn check_language(lang: &Arc<Box<dyn Language + Sync + Send>>, checks: &Mutex<Checks>) {
+    let lang_name = lang.get_lang_name();
+    let built = build_image(lang);
+    let pool = {
+        let mut checks = checks.lock().unwrap();
+        checks.building.remove(&lang_name);
+        checks.availability.insert(lang_name.clone(), built.is_ok());
+        checks.pool.clone()
+    };
+    if let Err(e) = built {
+        warn!("    - {}: Unavailable ({})", &lang_name, e);
+        return;
+    }
+    info!("    - {}: Available", &lang_name);
+    if let Some(pool) = pool {
+        pool.image_ready(&lang.get_image_name());
+    }
+
+    let version = match cmd!(
+        "docker",
+        "run",
+        "-t",
+        lang.get_image_name(),
+        "/bin/bash",
+        "-c",
+        lang.check_compiler_or_interpreter()
+    )
+    .stdout_capture()
+    .run()
+    {
+        Ok(res) => {
+            let mut output = String::from(std::str::from_utf8(&res.stdout).unwrap());
+            output.truncate(50);
+            info!("    - {}: {}", &lang_name, output);
+            Some(output)
+        }
+        Err(e) => {
+            warn!("    - {}: Unknown version ({})", &lang_name, e);
+            None
+        }
+    };
+    checks.lock().unwrap().versions.insert(lang_name, version);
+}
+
+/// Builds the image of `lang` from its Dockerfile, unless the image was already built from
+/// the same one and the same files.
+fn build_image(lang: &Arc<Box<dyn Language + Sync + Send>>) -> Result<(), Error> {
+    let dockerfile = get_dockerfile_path(lang);
+    let hash = hash_build_inputs(Path::new(&dockerfile), Path::new("."))?;
+    let image = lang.get_image_name();
+
+    let label = cmd!(
+        "docker",
+        "image",
+        "inspect",
+        "--format",
+        format!("{{{{ index .Config.Labels \"{}\" }}}}", BUILD_HASH_LABEL),
+        &image
+    )
+    .stdout_capture()
+    .stderr_null()
+    .unchecked()
+    .run()?;
+    if label.status.success() && String::from_utf8_lossy(&label.stdout).trim() == hash {
+        debug!("The image {} is up to date", image);
+        return Ok(());
+    }
+
+    info!("Building the image {}", image);
+    let res = cmd!(
+        "docker",
+        "build",
+        "-t",
+        &image,
+        "--label",
+        format!("{}={}", BUILD_HASH_LABEL, hash),
+        "-f",
+        &dockerfile,
+        "."
+    )
+    .stdout_null()
+    .stderr_capture()
+    .unchecked()
+    .run()?;
+    if !res.status.success() {
+        let stderr = String::from_utf8_lossy(&res.stderr);
+        let last_line = stderr.lines().rev().find(|line| !line.trim().is_empty()).unwrap_or("");
+        return Err(Error::other(format!("docker build failed: {}", last_line.trim())));
     }
+
+    Ok(())
+}
+
+fn get_dockerfile_path(lang: &Arc<Box<dyn Language + Sync + Send>>) -> String {
+    format!("images/Dockerfile.{}", lang.get_lang_name().to_lowercase())
+}
+
+/// Returns the SHA-1 of `dockerfile` and of the files its `COPY` and `ADD` instructions take
+/// from the build `context`, so that editing any of them rebuilds the image. Directories are
+/// hashed with all of their files.
+pub fn hash_build_inputs(dockerfile: &Path, context: &Path) -> Result<String, Error> {
+    let read = |path: &Path| std::fs::read(path).map_err(|e| path_error(path, e));
+    let contents = read(dockerfile)?;
+    let mut hasher = Sha1::new();
+    hasher.update(&contents);
+
+    let mut files = Vec::new();
+    for source in get_copied_sources(&String::from_utf8_lossy(&contents)) {
+        let path = context.join(&source);
+        let pattern = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+        if !pattern.contains(['*', '?']) {
+            list_files(&path, &mut files)?;
+            continue;
+        }
+        let parent = path.parent().unwrap_or(context);
+        for entry in std::fs::read_dir(parent).map_err(|e| path_error(parent, e))? {
+            let entry = entry?;
+            if matches_pattern(&pattern, &entry.file_name().to_string_lossy()) {
+                list_files(&entry.path(), &mut files)?;
+            }
+        }
+    }
+    files.sort();
+    files.dedup();
+    for file in files {
+        // The path is hashed as well, so that renaming a file changes the hash
+        hasher.update(file.strip_prefix(context).unwrap_or(&file).to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(read(&file)?);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the sources of the `COPY` and `ADD` instructions of a Dockerfile which come from the
+/// build context, leaving out the ones copied from another stage and the downloaded ones.
+pub fn get_copied_sources(dockerfile: &str) -> Vec<String> {
+    let mut sources = Vec::new();
+    for instruction in dockerfile.replace("\\\n", " ").lines() {
+        let mut words = instruction.split_whitespace();
+        match words.next().map(|word| word.to_ascii_uppercase()) {
+            Some(ref word) if word == "COPY" || word == "ADD" => {}
+            _ => continue,
+        }
+        let words: Vec<&str> = words.collect();
+        if words.iter().any(|word| word.starts_with("--from")) {
+            continue;
+        }
+        let args: Vec<String> = words
+            .into_iter()
+            .filter(|word| !word.starts_with("--"))
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .map(|arg| arg.trim().trim_matches('"').to_string())
+            .filter(|arg| !arg.is_empty())
+            .collect();
+        // The last argument is the destination in the image
+        if let Some((_destination, args)) = args.split_last() {
+            sources.extend(args.iter().filter(|arg| !arg.contains("://")).cloned());
+        }
+    }
+
+    sources
+}
+
+/// Adds `path` to `files`, or the files under it when it is a directory.
+fn list_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    let metadata = std::fs::metadata(path).map_err(|e| path_error(path, e))?;
+    if !metadata.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(path).map_err(|e| path_error(path, e))? {
+        list_files(&entry?.path(), files)?;
+    }
+
+    Ok(())
+}
+
+/// Whether the file `name` matches `pattern`, in which `*` stands for any characters and `?` for one.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // matches[j] is whether the pattern read so far matches the first j characters of the name
+    let mut matches = vec![false; name.len() + 1];
+    matches[0] = true;
+    for c in pattern {
+        if c == '*' {
+            for j in 1..=name.len() {
+                matches[j] = matches[j] || matches[j - 1];
+            }
+        } else {
+            for j in (1..=name.len()).rev() {
+                matches[j] = matches[j - 1] && (c == '?' || c == name[j - 1]);
+            }
+            matches[0] = false;
+        }
+    }
+
+    matches[name.len()]
+}
+
+fn path_error(path: &Path, e: Error) -> Error {
+    Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
+
+/// Sums up what the image and the version of a language are checked from.
+fn fingerprint(lang: &Arc<Box<dyn Language + Sync + Send>>) -> u64 {
+    use std::hash::{Hash, Hasher};
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    lang.get_image_name().hash(&mut hasher);
+    lang.check_compiler_or_interpreter().hash(&mut hasher);
+    hash_build_inputs(Path::new(&get_dockerfile_path(lang)), Path::new(".")).ok().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Checks the container limits of a section of the configuration, which are passed to `docker run`.
+fn check_limits(
+    section: &str,
+    cpu_load: &Option<String>,
+    ram_load: &Option<String>,
+    kernel_memory: &Option<String>,
+) -> Result<(), String> {
+    if let Some(cpu_load) = cpu_load {
+        match cpu_load.parse::<f64>() {
+            Ok(cpus) if cpus >= 0.0 => {}
+            _ => {
+                return Err(format!(
+                    "{}cpu_load = \"{}\" is not a number of cores, e.g. \"0.80\"",
+                    section, cpu_load
+                ))
+            }
+        }
+    }
+    let memory = regex::Regex::new(r"^[0-9]+[bkmgBKMG]?$").unwrap();
+    for (name, value) in [("ram_load", ram_load), ("kernel_memory", kernel_memory)].iter() {
+        if let Some(value) = value {
+            if !memory.is_match(value) {
+                return Err(format!(
+                    "{}{} = \"{}\" is not an amount of memory, e.g. \"250m\" or \"1g\"",
+                    section, name, value
+                ));
+            }
+        }
+    }
+
+    Ok(())
 }
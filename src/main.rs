@@ -1,6 +1,7 @@
 #[macro_use] extern crate log;
 #[macro_use] extern crate diesel;
 #[macro_use] extern crate serde_derive;
+#[macro_use] extern crate lazy_static;
 
 pub mod commands;
 pub mod lang_manager;
@@ -9,6 +10,7 @@ pub mod schema;
 pub mod models;
 pub mod dbl;
 pub mod file_logger;
+#[cfg(test)]
 mod test;
 
 use commands::*;
@@ -16,10 +18,11 @@ use lang_manager::LangManager;
 
 use serenity::{
     http::{ self, client::Http },
-    prelude::{ Client, Context, EventHandler },
+    prelude::{ Client, Context, EventHandler, RwLock, ShareMap },
     model::{
         channel::Embed,
-        prelude::{ Ready, Message, ResumedEvent, UserId },
+        event::MessageUpdateEvent,
+        prelude::{ Ready, Message, Reaction, ReactionType, ResumedEvent, UserId, GuildId },
     },
     framework::standard::{
         DispatchError, StandardFramework, Args, CommandGroup, HelpOptions, CommandResult, CommandOptions, CheckResult, help_commands,
@@ -33,7 +36,7 @@ use diesel::{
 use typemap::Key;
 
 use std::{
-    sync::{ Arc, Mutex },
+    sync::{ Arc, Mutex, atomic::{ AtomicBool, Ordering } },
     io::{ Error, ErrorKind, Read },
     collections::{ HashSet, HashMap },
     iter::FromIterator, str::FromStr, process::Command,
@@ -59,6 +62,98 @@ pub struct Settings {
     pub kernel_memory: String,
     pub compilation_timeout: u64,
     pub execution_timeout: u64,
+    #[serde(default = "default_output_limit")]
+    pub output_limit: usize,
+    #[serde(default)]
+    pub pool_size: usize,
+    #[serde(default)]
+    pub pool_sizes: HashMap<String, usize>,
+    #[serde(default = "default_stream_interval")]
+    pub stream_interval: u64,
+    #[serde(default = "default_attachment_limit")]
+    pub attachment_limit: usize,
+    #[serde(default = "default_source_file_limit")]
+    pub source_file_limit: u64,
+    #[serde(default = "default_rerun_window")]
+    pub rerun_window: u64,
+    #[serde(default = "default_bench_max_runs")]
+    pub bench_max_runs: usize,
+    #[serde(default = "default_pids_limit")]
+    pub pids_limit: u32,
+    #[serde(default = "default_build_concurrency")]
+    pub build_concurrency: usize,
+    /// Limits overriding the ones above for some languages, by lowercase language name once
+    /// resolved by `LangManager::resolve_language_limits`
+    #[serde(default)]
+    pub languages: HashMap<String, LanguageLimits>,
+}
+
+/// The limits of a `[languages.<name>]` table of the configuration, each of them
+/// overriding the setting of the same name.
+#[derive(Debug, Default, Deserialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageLimits {
+    pub cpu_load: Option<String>,
+    pub ram_load: Option<String>,
+    pub kernel_memory: Option<String>,
+    pub pids_limit: Option<u32>,
+    pub compilation_timeout: Option<u64>,
+    pub execution_timeout: Option<u64>,
+}
+
+impl Settings {
+    /// Returns these settings with the limits of the language named `lang_name` applied.
+    pub fn for_language(&self, lang_name: &str) -> Settings {
+        let mut settings = self.clone();
+        if let Some(limits) = self.languages.get(&lang_name.to_lowercase()) {
+            if let Some(ref cpu_load) = limits.cpu_load {
+                settings.cpu_load = cpu_load.clone();
+            }
+            if let Some(ref ram_load) = limits.ram_load {
+                settings.ram_load = ram_load.clone();
+            }
+            if let Some(ref kernel_memory) = limits.kernel_memory {
+                settings.kernel_memory = kernel_memory.clone();
+            }
+            settings.pids_limit = limits.pids_limit.unwrap_or(settings.pids_limit);
+            settings.compilation_timeout = limits.compilation_timeout.unwrap_or(settings.compilation_timeout);
+            settings.execution_timeout = limits.execution_timeout.unwrap_or(settings.execution_timeout);
+        }
+
+        settings
+    }
+}
+
+fn default_output_limit() -> usize {
+    64 * 1024
+}
+
+fn default_stream_interval() -> u64 {
+    2000
+}
+
+fn default_attachment_limit() -> usize {
+    1024 * 1024
+}
+
+fn default_source_file_limit() -> u64 {
+    100 * 1024
+}
+
+fn default_rerun_window() -> u64 {
+    10 * 60
+}
+
+fn default_bench_max_runs() -> usize {
+    20
+}
+
+fn default_build_concurrency() -> usize {
+    2
+}
+
+fn default_pids_limit() -> u32 {
+    512
 }
 
 impl Key for Settings {
@@ -101,6 +196,163 @@ impl EventHandler for Handler {
     fn message(&self, _: Context, _msg: Message) {
 
     }
+
+    fn message_update(&self, mut ctx: Context, _old: Option<Message>, _new: Option<Message>, event: MessageUpdateEvent) {
+        // Messages are also updated when their embeds are resolved, only edits of the content matter
+        if event.content.is_none() {
+            return;
+        }
+
+        let (reply_id, prefix) = {
+            let data = ctx.data.read();
+            let settings = data.get::<Settings>().unwrap().lock().unwrap();
+            let window = std::time::Duration::from_secs(settings.rerun_window);
+            match data.get::<ExecReplies>().and_then(|replies| replies.get(&event.id)) {
+                Some(reply) if reply.created.elapsed() < window => (reply.reply_id, settings.command_prefix.clone()),
+                _ => return,
+            }
+        };
+
+        let msg = match event.channel_id.message(&ctx, event.id) {
+            Ok(msg) => msg,
+            Err(_) => return,
+        };
+        let args = match commands::exec::get_exec_args(&msg.content, &prefix) {
+            Some(args) => args,
+            None => return,
+        };
+        if is_banned(&ctx, msg.author.id, msg.guild_id) {
+            return;
+        }
+        if let Some(seconds) = get_rerun_delay(&ctx, msg.author.id) {
+            let _ = msg.reply(&ctx, format!("Try this again in {} seconds.", seconds));
+            return;
+        }
+        let reply = match event.channel_id.message(&ctx, reply_id) {
+            Ok(reply) => reply,
+            Err(_) => return,
+        };
+
+        info!("Re-running the exec command of message {}", msg.id);
+        if let Err(e) = commands::exec::exec_message(&mut ctx, &msg, &args, Some(reply)) {
+            error!("An error occured while re-running an exec query: {:?}", e);
+        }
+    }
+
+    fn reaction_add(&self, mut ctx: Context, reaction: Reaction) {
+        let emoji = match &reaction.emoji {
+            ReactionType::Unicode(emoji) => emoji.trim_end_matches('\u{fe0f}').to_string(),
+            _ => return,
+        };
+        if emoji != commands::exec::RERUN_REACTION
+            && emoji != commands::exec::DELETE_REACTION
+            && emoji != commands::exec::CONFIRM_REACTION
+        {
+            return;
+        }
+        if reaction.user_id == ctx.cache.read().user.id {
+            return;
+        }
+
+        let (command_id, prefix, pending_lang) = {
+            let data = ctx.data.read();
+            let settings = data.get::<Settings>().unwrap().lock().unwrap();
+            let window = std::time::Duration::from_secs(settings.rerun_window);
+            let replies = match data.get::<ExecReplies>() {
+                Some(replies) => replies,
+                None => return,
+            };
+            match commands::exec::find_exec_command(replies, reaction.message_id) {
+                Some(command_id) if replies[&command_id].created.elapsed() < window => (
+                    command_id,
+                    settings.command_prefix.clone(),
+                    replies[&command_id].pending_lang.clone(),
+                ),
+                _ => return,
+            }
+        };
+        if is_banned(&ctx, reaction.user_id, reaction.guild_id) {
+            return;
+        }
+        let msg = match reaction.channel_id.message(&ctx, command_id) {
+            Ok(msg) => msg,
+            Err(_) => return,
+        };
+
+        if emoji == commands::exec::CONFIRM_REACTION {
+            let lang_code = match pending_lang {
+                Some(lang_code) if reaction.user_id == msg.author.id => lang_code,
+                _ => return,
+            };
+            if let Some(seconds) = get_rerun_delay(&ctx, msg.author.id) {
+                // Removing the reaction lets the author confirm again once the delay is over
+                let _ = reaction.delete(&ctx);
+                let _ = msg.reply(&ctx, format!("Try this again in {} seconds.", seconds));
+                return;
+            }
+            let args = match commands::exec::get_exec_args(&msg.content, &prefix) {
+                Some(args) => commands::exec::get_confirmed_lang_args(&args, &lang_code),
+                None => return,
+            };
+            let reply = match reaction.channel_id.message(&ctx, reaction.message_id) {
+                Ok(reply) => reply,
+                Err(_) => return,
+            };
+            let _ = reaction.delete(&ctx);
+            let _ = reaction.channel_id.delete_reaction(
+                &ctx,
+                reaction.message_id,
+                None,
+                ReactionType::Unicode(commands::exec::CONFIRM_REACTION.to_string()),
+            );
+
+            info!("Running the exec command of message {} as {}", msg.id, lang_code);
+            if let Err(e) = commands::exec::exec_message(&mut ctx, &msg, &args, Some(reply)) {
+                error!("An error occured while running a confirmed exec query: {:?}", e);
+            }
+        } else if emoji == commands::exec::RERUN_REACTION {
+            if reaction.user_id != msg.author.id {
+                return;
+            }
+            // Removing the reaction lets the author use it again
+            let _ = reaction.delete(&ctx);
+            if let Some(seconds) = get_rerun_delay(&ctx, msg.author.id) {
+                let _ = msg.reply(&ctx, format!("Try this again in {} seconds.", seconds));
+                return;
+            }
+            let args = match commands::exec::get_exec_args(&msg.content, &prefix) {
+                Some(args) => args,
+                None => return,
+            };
+            let reply = match reaction.channel_id.message(&ctx, reaction.message_id) {
+                Ok(reply) => reply,
+                Err(_) => return,
+            };
+
+            info!("Re-running the exec command of message {}", msg.id);
+            if let Err(e) = commands::exec::exec_message(&mut ctx, &msg, &args, Some(reply)) {
+                error!("An error occured while re-running an exec query: {:?}", e);
+            }
+        } else {
+            if reaction.user_id != msg.author.id && !is_admin(&ctx, reaction.user_id, reaction.guild_id) {
+                return;
+            }
+            if let Err(e) = reaction.channel_id.delete_message(&ctx, reaction.message_id) {
+                error!("Could not delete the reply to an exec query: {:?}", e);
+                return;
+            }
+
+            let mut data = ctx.data.write();
+            let reply = data.get_mut::<ExecReplies>().unwrap().remove(&command_id);
+            if let Some(reply) = reply {
+                let db = data.get::<DbPool>().unwrap();
+                if let Err(e) = models::Snippet::delete(&reply.snippets, db) {
+                    warn!("Could not delete snippets from db: {}", e);
+                }
+            }
+            info!("Deleted the result of the exec command of message {}", msg.id);
+        }
+    }
 }
 
 fn presence_status_thread(user_id: UserId, ctx: Arc<Mutex<Context>>) {
@@ -253,6 +505,42 @@ fn cargo_test_thread(settings: Settings) {
     });
 }
 
+/// Forgets the results of `exec` commands once they cannot be re-run anymore, and takes back
+/// the reactions offering to.
+fn exec_replies_cleanup_thread(data: Arc<RwLock<ShareMap>>, http: Arc<Http>) {
+    std::thread::spawn(move || {
+        loop {
+            std::thread::sleep(std::time::Duration::from_secs(30));
+
+            let expired = {
+                let mut data = data.write();
+                let window = {
+                    let settings = data.get::<Settings>().unwrap().lock().unwrap();
+                    std::time::Duration::from_secs(settings.rerun_window)
+                };
+                match data.get_mut::<ExecReplies>() {
+                    Some(replies) => commands::exec::take_expired_replies(replies, window),
+                    None => continue,
+                }
+            };
+            for reply in expired {
+                let reactions = match reply.pending_lang {
+                    Some(_) => vec![commands::exec::CONFIRM_REACTION],
+                    None => vec![commands::exec::RERUN_REACTION, commands::exec::DELETE_REACTION],
+                };
+                for reaction in reactions {
+                    let _ = reply.channel_id.delete_reaction(
+                        &*http,
+                        reply.reply_id,
+                        None,
+                        ReactionType::Unicode(reaction.to_string()),
+                    );
+                }
+            }
+        }
+    });
+}
+
 fn snippets_cleanup_thread() {
     std::thread::spawn(move || {
         // Periodic snippets directory cleanup
@@ -279,6 +567,32 @@ fn snippets_cleanup_thread() {
     });
 }
 
+/// Set on SIGHUP, and cleared by `reload_signal_thread` when it reloads the configuration.
+static RELOAD_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn request_reload(_: libc::c_int) {
+    RELOAD_REQUESTED.store(true, Ordering::SeqCst);
+}
+
+fn reload_signal_thread(data: Arc<RwLock<ShareMap>>) {
+    unsafe {
+        libc::signal(libc::SIGHUP, request_reload as *const () as libc::sighandler_t);
+    }
+    std::thread::spawn(move || {
+        // Reloads are done out of the signal handler, which can only set a flag
+        loop {
+            if RELOAD_REQUESTED.swap(false, Ordering::SeqCst) {
+                info!("SIGHUP received");
+                if let Err(e) = reload_configuration(&data) {
+                    error!("The configuration was not reloaded: {}", e);
+                }
+            }
+
+            std::thread::sleep(std::time::Duration::from_secs(1));
+        }
+    });
+}
+
 pub type DbPoolType = Arc<Pool<ConnectionManager<SqliteConnection>>>;
 pub struct DbPool(DbPoolType);
 
@@ -291,6 +605,37 @@ impl Key for Bans {
     type Value = HashMap<serenity::model::prelude::UserId, Vec<models::Ban>>;
 }
 
+/// Whether `user` is banned from using the bot in `guild`.
+fn is_banned(ctx: &Context, user: UserId, guild: Option<GuildId>) -> bool {
+    let data = ctx.data.read();
+    let bans = data.get::<Bans>().unwrap();
+    match bans.get(&user) {
+        Some(bans) => bans.iter().any(|ban| {
+            ban.is_banned_for_guild(guild)
+        }),
+        None => false,
+    }
+}
+
+/// Seconds `user` must still wait before running code again, if any.
+fn get_rerun_delay(ctx: &Context, user: UserId) -> Option<u64> {
+    let data = ctx.data.read();
+    data.get::<LastExecutions>()
+        .and_then(|executions| commands::exec::get_rerun_delay(executions, user))
+}
+
+/// Whether `user` is an administrator of `guild`.
+fn is_admin(ctx: &Context, user: UserId, guild: Option<GuildId>) -> bool {
+    let member = match guild {
+        Some(guild) => guild.member(ctx, user),
+        None => return false,
+    };
+    match member {
+        Ok(member) => member.permissions(&ctx.cache).map(|permissions| permissions.administrator()).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
 fn get_guilds(ctx: &Context) -> Result<usize, serenity::Error> {
     let mut count = 0;
     let mut last_guild_id = 0;
@@ -310,11 +655,22 @@ fn get_guilds(ctx: &Context) -> Result<usize, serenity::Error> {
 }
 
 fn init_settings() -> Settings {
-    let mut f = std::fs::File::open("config.toml").expect("Could not find the config.toml file. Please copy config.toml.example to config.toml and edit the resulting file");
+    match read_settings() {
+        Ok(settings) => settings,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => panic!("Could not find the config.toml file. Please copy config.toml.example to config.toml and edit the resulting file"),
+        Err(e) => panic!("{}", e),
+    }
+}
+
+/// Reads `config.toml`, e.g. when the configuration is reloaded.
+pub fn read_settings() -> Result<Settings, Error> {
+    let mut f = std::fs::File::open("config.toml")
+        .map_err(|e| Error::new(e.kind(), format!("Could not open config.toml: {}", e)))?;
     let mut contents = String::new();
     f.read_to_string(&mut contents)
-        .expect("Could not read configuration file");
-    toml::from_str(&contents).expect("Could not deserialize configuration")
+        .map_err(|e| Error::new(e.kind(), format!("Could not read configuration file: {}", e)))?;
+    toml::from_str(&contents)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("Could not deserialize configuration: {}", e)))
 }
 
 fn init_logging(settings: &Settings) {
@@ -364,7 +720,7 @@ fn admin_check(ctx: &mut Context, msg: &Message, _: &mut Args, _: &CommandOption
 }
 
 #[group]
-#[commands(git, support, invite, exec, languages, versions, stats)]
+#[commands(git, support, invite, exec, bench, asm, fmt, lint, languages, packages, versions, stats)]
 #[description = ":desktop: Basic"]
 struct General;
 
@@ -376,7 +732,7 @@ struct Admin;
 
 #[group]
 #[owners_only]
-#[commands(logs)]
+#[commands(logs, pool, reload)]
 #[description = ":robot: Bot owner"]
 struct Owner;
 
@@ -389,7 +745,7 @@ fn main() {
         tools::update_db::update_db();
     }
 
-    let settings = init_settings();
+    let mut settings = init_settings();
     let command_prefix = settings.command_prefix.clone();
     let owners = HashSet::from_iter(settings.bot_owners.clone());
     init_logging(&settings);
@@ -406,8 +762,12 @@ fn main() {
     models::Ban::cleanup_outdated_bans(&pool);
 
     let mut lang_manager = LangManager::new();
-    lang_manager.check_available_languages();
-    lang_manager.check_languages_versions();
+    if let Err(e) = lang_manager.resolve_language_limits(&mut settings) {
+        panic!("Invalid configuration: {}", e);
+    }
+    // Languages become available while the bot connects, as their images get ready
+    lang_manager.check_languages(&lang_manager.get_language_names(), settings.build_concurrency);
+    lang_manager.init_pool(&settings);
 
     {
         let mut data = client.data.write();
@@ -415,6 +775,8 @@ fn main() {
         data.insert::<LangManager>(Arc::new(Mutex::new(lang_manager)));
         data.insert::<DbPool>(pool.clone());
         data.insert::<Bans>(models::Ban::get_bans(&pool));
+        data.insert::<ExecReplies>(HashMap::new());
+        data.insert::<LastExecutions>(HashMap::new());
     }
 
     client.with_framework(StandardFramework::new()
@@ -422,20 +784,11 @@ fn main() {
             .prefix(&command_prefix)
             .owners(owners))
         .before(|ctx, msg, _cmd_name| {
-            let data = ctx.data.read();
-            let bans = data.get::<Bans>().unwrap();
-            match bans.get(&msg.author.id) {
-                Some(bans) => {
-                    let banned = bans.iter().any(|ban| {
-                        ban.is_banned_for_guild(msg.guild_id)
-                    });
-                    if banned {
-                        let _ = msg.reply(&ctx, "You cannot run commands while being banned.");
-                    }
-                    !banned
-                },
-                None => true,
+            let banned = is_banned(ctx, msg.author.id, msg.guild_id);
+            if banned {
+                let _ = msg.reply(&ctx, "You cannot run commands while being banned.");
             }
+            !banned
         })
         // Set a function that's called whenever a command's execution didn't complete for one
         // reason or another. For example, when a user has exceeded a rate-limit or a command
@@ -458,13 +811,15 @@ fn main() {
         // Time out for exec: Can't be used more than 2 times per 30 seconds, with a 5 second delay
         //.bucket("exec_bucket", 5, 30, 2)
         // Can't be used more than once per 5 seconds:
-        .bucket("exec_bucket", |b| b.delay(5))
+        .bucket("exec_bucket", |b| b.delay(commands::exec::EXEC_DELAY as i64))
         .group(&GENERAL_GROUP)
         .group(&ADMIN_GROUP)
         .group(&OWNER_GROUP)
     );
 
     snippets_cleanup_thread();
+    exec_replies_cleanup_thread(client.data.clone(), client.cache_and_http.http.clone());
+    reload_signal_thread(client.data.clone());
     cargo_test_thread(init_settings());
 
     if let Err(why) = client.start() {
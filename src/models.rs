@@ -271,7 +271,8 @@ impl LangStat {
 }
 
 impl Snippet {
-    pub fn save(contents: String, lang: &str, author: UserId, msg_guild: Option<GuildId>, db: &DbPoolType) -> Result<usize, diesel::result::Error> {
+    /// Stores a snippet and returns its id.
+    pub fn save(contents: String, lang: &str, author: UserId, msg_guild: Option<GuildId>, db: &DbPoolType) -> Result<i32, diesel::result::Error> {
         let author = User::get(author, &db);
 
         let msg_guild = match msg_guild {
@@ -289,6 +290,25 @@ impl Snippet {
             language.eq(lang),
             guild.eq(msg_guild),
             run_time.eq(epoch),
+        )).execute(&db)?;
+        // The id of the last insert is kept per connection, so other inserts cannot get in the way
+        diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>("last_insert_rowid()")).get_result::<i32>(&db)
+    }
+
+    /// Records the resources used by the program of the snippets `ids`.
+    pub fn set_usage(ids: &[i32], memory: u64, user_cpu: std::time::Duration, system_cpu: std::time::Duration, db: &DbPoolType) -> Result<usize, diesel::result::Error> {
+        let db = db.get().unwrap();
+        use schema::snippet::dsl::*;
+        diesel::update(snippet.filter(id.eq_any(ids))).set((
+            peak_memory.eq(memory as i64),
+            user_time.eq(user_cpu.as_millis() as i32),
+            system_time.eq(system_cpu.as_millis() as i32),
         )).execute(&db)
     }
+
+    pub fn delete(ids: &[i32], db: &DbPoolType) -> Result<usize, diesel::result::Error> {
+        let db = db.get().unwrap();
+        use schema::snippet::dsl::*;
+        diesel::delete(snippet.filter(id.eq_any(ids))).execute(&db)
+    }
 }
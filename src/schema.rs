@@ -23,6 +23,9 @@ table! {
         language -> Text,
         guild -> Nullable<Text>,
         run_time -> Text,
+        peak_memory -> Nullable<BigInt>,
+        user_time -> Nullable<Integer>,
+        system_time -> Nullable<Integer>,
     }
 }
 
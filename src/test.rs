@@ -1,4 +1,10 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
 use crate::{ commands, Settings, LangManager };
+use crate::commands::exec::{ ExecOptions, ExecResult, CodeBlock, SourceFile };
+use crate::commands::exec::backend::{ BoundedBuffer, ContainerPool, ExecutionBackend, FakeBackend, FakeSession };
 
 #[allow(dead_code)]
 fn test_lang(code: &str, lang: &str, ret_code: i32, ignore_compil_stdout: bool, ret_str: &str) {
@@ -11,7 +17,8 @@ fn test_lang(code: &str, lang: &str, ret_code: i32, ignore_compil_stdout: bool,
     let settings = get_test_settings();
 
     let lang = commands::exec::get_lang(&lang_manager, lang).unwrap();
-    let res = commands::exec::run_code(&settings, code.into(), lang, user, None, None);
+    let backend = lang_manager.get_backend();
+    let res = commands::exec::run_code(&settings, backend.as_ref(), vec![code.into()], lang, &ExecOptions::default(), user, None, None);
     let (compilation, execution, _, _) = res.unwrap();
 
     if compilation.timed_out {
@@ -49,6 +56,39 @@ fn test_lang(code: &str, lang: &str, ret_code: i32, ignore_compil_stdout: bool,
     }
 }
 
+fn run_fake(code: &str, lang: &str, options: &ExecOptions, settings: &Settings, backend: &FakeBackend) -> (ExecResult, ExecResult, FakeSession) {
+    run_fake_files(vec![code.into()], lang, options, settings, backend)
+}
+
+fn run_fake_files(files: Vec<SourceFile>, lang: &str, options: &ExecOptions, settings: &Settings, backend: &FakeBackend) -> (ExecResult, ExecResult, FakeSession) {
+    let mut lang_manager = LangManager::new();
+    let languages = lang_manager.get_languages().clone();
+    for (_codes, boxed_lang) in languages {
+        lang_manager.set_language_available(boxed_lang.get_lang_name(), true);
+    }
+    let user = serenity::model::id::UserId::from(123_456_u64);
+
+    let lang = commands::exec::get_lang(&lang_manager, lang).unwrap();
+    let res = commands::exec::run_code(settings, backend, files, lang, options, user, None, None);
+    let (compilation, execution, _, _) = res.unwrap();
+
+    let mut sessions = backend.sessions();
+    assert_eq!(sessions.len(), 1);
+    let session = sessions.remove(0);
+    assert!(session.torn_down, "Session was not torn down");
+
+    (compilation, execution, session)
+}
+
+fn output(exit_code: i32, stdout: &str, stderr: &str) -> ExecResult {
+    ExecResult {
+        exit_code: Some(exit_code),
+        stdout: stdout.into(),
+        stderr: stderr.into(),
+        ..Default::default()
+    }
+}
+
 fn get_test_settings() -> Settings {
     let mut settings = Settings::default();
     settings.cpu_load = "0.000".into();
@@ -182,3 +222,1300 @@ fn test_ocaml() {
     test_lang("print_string \"test\"", "ocaml", 0, false, "test");
     test_lang("let main = print_string \"test\"; exit 0", "ocaml", 0, false, "test");
 }
+
+#[test]
+fn test_fake_compiled() {
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "rustc" => output(0, "", ""),
+        _ => output(0, "test", ""),
+    });
+    let (compilation, execution, session) = run_fake("print!(\"test\");", "rust", &ExecOptions::default(), &get_test_settings(), &backend);
+
+    assert_eq!(session.image, "rustacean-rust");
+    assert_eq!(session.steps.len(), 2);
+    let src = &session.steps[0].command[2];
+    assert_eq!(session.steps[0].command, vec!["rustc".to_string(), "--edition=2018".into(), src.clone(), "-o".into(), format!("{}.out", src)]);
+    assert_eq!(session.steps[1].command, vec![format!("{}.out", src)]);
+    assert!(session.files[&std::path::PathBuf::from(src)].starts_with("fn main() {"));
+
+    assert_eq!(compilation.exit_code, Some(0));
+    assert_eq!(execution.exit_code, Some(0));
+    assert_eq!(execution.stdout, "test");
+}
+
+#[test]
+fn test_fake_interpreted() {
+    let backend = FakeBackend::new(|session, step| {
+        let script = &session.files[&std::path::PathBuf::from(&step.command[1])];
+        output(3, "", script)
+    });
+    let (compilation, execution, session) = run_fake("print('test')", "python", &ExecOptions::default(), &get_test_settings(), &backend);
+
+    assert_eq!(session.steps.len(), 1);
+    assert_eq!(session.steps[0].command[0], "python3");
+    assert_eq!(session.files.len(), 2);
+    assert_eq!(compilation.exit_code, None);
+    assert_eq!(execution.exit_code, Some(3));
+    assert_eq!(execution.stderr, "print('test')");
+}
+
+#[test]
+fn test_fake_compilation_failure() {
+    let backend = FakeBackend::new(|_, _| output(1, "", "error: expected `;`"));
+    let (compilation, execution, session) = run_fake("printf(\"test\")", "c", &ExecOptions::default(), &get_test_settings(), &backend);
+
+    assert_eq!(session.steps.len(), 1);
+    assert_eq!(session.steps[0].command[0], "gcc");
+    assert_eq!(compilation.exit_code, Some(1));
+    assert_eq!(compilation.stderr, "error: expected `;`");
+    assert_eq!(execution.exit_code, None);
+    assert!(!execution.timed_out);
+}
+
+#[test]
+fn test_fake_chained_compilation() {
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let (_, _, session) = run_fake("section .text", "asmx64", &ExecOptions::default(), &get_test_settings(), &backend);
+
+    assert_eq!(session.steps.len(), 3);
+    assert_eq!(session.steps[0].command[0], "nasm");
+    assert_eq!(session.steps[1].command[0], "ld");
+}
+
+#[test]
+fn test_fake_timeouts() {
+    let mut settings = get_test_settings();
+    settings.compilation_timeout = 30;
+    settings.execution_timeout = 10;
+
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "g++" => output(0, "", ""),
+        _ => ExecResult { duration: Duration::from_secs(10), ..output(0, "test", "") },
+    });
+    let (compilation, execution, _) = run_fake("for (;;) {}", "cpp", &ExecOptions::default(), &settings, &backend);
+    assert!(!compilation.timed_out);
+    assert!(execution.timed_out);
+    assert_eq!(execution.exit_code, None);
+    assert!(execution.stdout.is_empty());
+
+    let backend = FakeBackend::new(|_, _| ExecResult { duration: Duration::from_secs(45), ..Default::default() });
+    let (compilation, execution, session) = run_fake("fn main() {}", "rust", &ExecOptions::default(), &settings, &backend);
+    assert!(compilation.timed_out);
+    assert!(!execution.timed_out);
+    assert_eq!(session.steps.len(), 1);
+}
+
+#[test]
+fn test_language_limits() {
+    use crate::LanguageLimits;
+
+    let lang_manager = LangManager::new();
+    let mut settings = get_test_settings();
+    settings.compilation_timeout = 30;
+    settings.execution_timeout = 10;
+    settings.languages = toml::from_str(
+        "[kt]\nram_load = \"1g\"\ncompilation_timeout = 90\n[Lua]\nexecution_timeout = 5\n[cpp]\ncompilation_timeout = 60",
+    ).unwrap();
+    lang_manager.resolve_language_limits(&mut settings).unwrap();
+    let mut names: Vec<&String> = settings.languages.keys().collect();
+    names.sort();
+    assert_eq!(names, vec!["cpp", "kotlin", "lua"]);
+    let kotlin = settings.for_language("Kotlin");
+    assert_eq!((kotlin.ram_load.as_str(), kotlin.cpu_load.as_str()), ("1g", "0.000"));
+    assert_eq!((kotlin.compilation_timeout, kotlin.execution_timeout), (90, 10));
+    assert_eq!(settings.for_language("Lua").execution_timeout, 5);
+    assert_eq!(settings.for_language("Rust").compilation_timeout, 30);
+    assert_eq!(commands::languages::format_limits(&kotlin), "0.000 CPU, 1g RAM, 90s to compile, 10s to run");
+
+    // The limits are applied to the steps of the language
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let (_, _, session) = run_fake("int main() {}", "cpp", &ExecOptions::default(), &settings, &backend);
+    assert_eq!(session.steps.iter().map(|step| step.timeout_seconds).collect::<Vec<_>>(), vec![60, 10]);
+
+    let resolve = |languages: &str| {
+        let mut settings = get_test_settings();
+        settings.languages = toml::from_str(languages).unwrap();
+        lang_manager.resolve_language_limits(&mut settings).map_err(|e| e.to_string())
+    };
+    assert!(resolve("[kotln]\nram_load = \"1g\"").unwrap_err().contains("did you mean `kotlin`?"));
+    assert!(resolve("[py]\nexecution_timeout = 5\n[python]\nexecution_timeout = 6").unwrap_err().contains("set twice"));
+    assert!(resolve("[java]\nram_load = \"lots\"").unwrap_err().contains("[languages.java] ram_load"));
+    assert!(resolve("[java]\ncpu_load = \"-1\"").is_err());
+    assert!(resolve("[java]\ncpu_load = \"2\"\nkernel_memory = \"50M\"").is_ok());
+    assert!(toml::from_str::<LanguageLimits>("ram = \"1g\"").is_err(), "Misspelled limits are refused");
+}
+
+#[test]
+fn test_fake_stdin() {
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "rustc" => output(0, "", ""),
+        _ => output(0, step.stdin.as_deref().unwrap_or("<no input>"), ""),
+    });
+    let options = ExecOptions { stdin: Some("1 2\n".into()), ..Default::default() };
+    let (_, execution, _) = run_fake("let mut s = String::new();", "rust", &options, &get_test_settings(), &backend);
+    assert_eq!(execution.stdout, "1 2\n");
+
+    let backend = FakeBackend::new(|_, step| {
+        assert_eq!(step.command[0], "rustc");
+        assert!(step.stdin.is_none());
+        output(1, "", "")
+    });
+    run_fake("fn main() {}", "rust", &options, &get_test_settings(), &backend);
+}
+
+#[test]
+fn test_fake_args_and_env() {
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "javac" => {
+            assert!(step.env.is_empty());
+            output(0, "", "")
+        },
+        _ => output(0, "", ""),
+    });
+    let options = ExecOptions {
+        args: vec!["foo".into(), "bar baz".into()],
+        env: vec![("DEBUG".into(), "1".into())],
+        ..Default::default()
+    };
+    let (_, _, session) = run_fake("System.out.print(args[0]);", "java", &options, &get_test_settings(), &backend);
+
+    let execution = &session.steps[1];
+    assert_eq!(execution.command[0], "java");
+    assert_eq!(&execution.command[2..], &["foo".to_string(), "bar baz".into()]);
+    assert_eq!(execution.env, options.env);
+}
+
+#[test]
+fn test_exec_options() {
+    use commands::exec::parse_exec_options;
+
+    let options = parse_exec_options("  --arg foo --arg \"bar baz\"\n--env RUST_BACKTRACE=1 --env EMPTY=").unwrap();
+    assert_eq!(options.args, vec!["foo".to_string(), "bar baz".into()]);
+    assert_eq!(options.env, vec![("RUST_BACKTRACE".to_string(), "1".to_string()), ("EMPTY".into(), "".into())]);
+    assert_eq!(parse_exec_options("").unwrap(), ExecOptions::default());
+
+    assert!(parse_exec_options("--arg").is_err());
+    assert!(parse_exec_options("--env FOO").is_err());
+    assert!(parse_exec_options("--env 1FOO=bar").is_err());
+    assert!(parse_exec_options("--env =bar").is_err());
+    assert!(parse_exec_options("--verbose").is_err());
+    let args = commands::exec::get_exec_args("~exec this prints hi\n```py\nprint(\"hi\")\n```", "~").unwrap();
+    assert_eq!(parse_exec_options(args.split("```").next().unwrap()).unwrap(), ExecOptions::default(), "Text before the code is not an option");
+    assert_eq!(parse_exec_options("with --arg foo please").unwrap().args, vec!["foo".to_string()]);
+    assert!(parse_exec_options("--hex").unwrap().hex);
+    assert!(parse_exec_options(&"--arg aaaaaaaaaa ".repeat(100)).is_err());
+    assert_eq!(parse_exec_options("--flag -O2 --flag -std=c99").unwrap().flags, vec!["-O2".to_string(), "-std=c99".into()]);
+    assert!(parse_exec_options("--flag").is_err());
+    assert_eq!(parse_exec_options("--bench 10 --bench 5").unwrap().bench, Some(5));
+    assert!(parse_exec_options("--bench 0").is_err());
+    assert!(parse_exec_options("--bench many").is_err());
+    let options = parse_exec_options("--emit llvm-ir --opt s").unwrap();
+    assert_eq!(options.emit, Some(commands::exec::language::Emit::LlvmIr));
+    assert_eq!(options.opt_level.as_deref(), Some("s"));
+    assert!(parse_exec_options("--emit wasm").is_err());
+    assert!(parse_exec_options("--emit asm --opt 4").is_err());
+    assert!(parse_exec_options("--opt 2").is_err(), "`--opt` needs `--emit`");
+    assert!(parse_exec_options("--emit asm --bench 5").is_err());
+    assert_eq!(parse_exec_options("--lang Python").unwrap().lang.as_deref(), Some("python"));
+    assert_eq!(
+        parse_exec_options(&commands::exec::get_confirmed_lang_args(" --lang c", "python")).unwrap().lang.as_deref(),
+        Some("c")
+    );
+}
+
+#[test]
+fn test_bench_stats() {
+    use commands::exec::{ format_bench_duration, BenchResult };
+
+    let ms = Duration::from_millis;
+    let bench = BenchResult { runs: vec![ms(4), ms(1), ms(3), ms(2)], requested: 4, stopped: None };
+    assert_eq!(bench.min(), ms(1));
+    assert_eq!(bench.max(), ms(4));
+    assert_eq!(bench.median(), Duration::from_micros(2500));
+    assert_eq!(bench.stddev().as_micros(), 1118);
+    let bench = BenchResult { runs: vec![ms(3), ms(1), ms(2)], ..Default::default() };
+    assert_eq!(bench.median(), ms(2));
+    assert_eq!(BenchResult::default().median(), Duration::default());
+
+    assert_eq!(format_bench_duration(Duration::from_micros(250)), "250\u{b5}s");
+    assert_eq!(format_bench_duration(Duration::from_micros(1250)), "1.25ms");
+    assert_eq!(format_bench_duration(Duration::from_millis(2500)), "2.50s");
+}
+
+#[test]
+fn test_fake_bench_short_runs() {
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+
+    let mut settings = get_test_settings();
+    settings.execution_timeout = 10;
+    settings.bench_max_runs = 5;
+    let micros = Duration::from_micros;
+    let overheads = [micros(3000), micros(2100), micros(2600)];
+    let runs = [micros(2600), micros(2100), micros(9100), micros(132_100), micros(2350)];
+    let (trues, execs) = (AtomicUsize::new(0), AtomicUsize::new(0));
+    let backend = FakeBackend::new(move |_, step| match step.command[0].as_str() {
+        "rustc" => output(0, "", ""),
+        "true" => ExecResult { duration: overheads[trues.fetch_add(1, Ordering::SeqCst)], ..output(0, "", "") },
+        // The first run shows the output, the benchmark follows
+        _ => match execs.fetch_add(1, Ordering::SeqCst) {
+            0 => output(0, "", ""),
+            i => ExecResult { duration: runs[i - 1], ..output(0, "", "") },
+        },
+    });
+    let options = ExecOptions { bench: Some(5), ..Default::default() };
+    let (_, execution, _) = run_fake("", "rust", &options, &settings, &backend);
+
+    // Runs of a few milliseconds are reported as they were timed, less the fastest no-op
+    let bench = execution.bench.unwrap();
+    assert_eq!(bench.runs, vec![micros(500), micros(0), micros(7000), micros(130_000), micros(250)]);
+    assert_eq!(bench.min(), micros(0));
+    assert_eq!(bench.median(), micros(500));
+    assert_eq!(bench.max(), micros(130_000));
+}
+
+#[test]
+fn test_resource_usage() {
+    use commands::exec::{ format_memory, ResourceUsage };
+
+    let usage = ResourceUsage::parse("2048 0.25 0.01\n").unwrap();
+    assert_eq!(usage.peak_memory, 2 * 1024 * 1024);
+    assert_eq!(usage.user_time, Duration::from_millis(250));
+    assert_eq!(usage.system_time, Duration::from_millis(10));
+    // GNU time reports how the command exited on the line before
+    let usage = ResourceUsage::parse("Command exited with non-zero status 1\n1024 0.00 0.00\n").unwrap();
+    assert_eq!(usage.peak_memory, 1024 * 1024);
+    assert!(ResourceUsage::parse("").is_none());
+    assert!(ResourceUsage::parse("cat: /tmp/.usage: No such file or directory").is_none());
+    assert!(ResourceUsage::parse("1024 0.00").is_none());
+
+    assert_eq!(format_memory(512), "512 B");
+    assert_eq!(format_memory(1536), "1.5 KiB");
+    assert_eq!(format_memory(250 * 1024 * 1024), "250.0 MiB");
+}
+
+#[test]
+fn test_termination() {
+    use commands::exec::{ signal_name, Termination };
+    use commands::exec::backend::{ get_signal, parse_pids_events, parse_signal };
+
+    assert_eq!(parse_signal("Command terminated by signal 11\n0 0.00 0.00\n"), Some(11));
+    assert_eq!(parse_signal("Command exited with non-zero status 139\n0 0.00 0.00\n"), None);
+    assert_eq!(parse_signal(""), None);
+    assert_eq!(get_signal(Some(1), Some("Command terminated by signal 11\n")), Some(11));
+    assert_eq!(get_signal(Some(137), None), Some(9));
+    assert_eq!(get_signal(Some(137), Some("")), Some(9), "An empty report falls back on the exit code");
+    assert_eq!(get_signal(Some(1), Some("")), None);
+    assert_eq!(parse_pids_events("max 0\n"), 0);
+    assert_eq!(parse_pids_events("max 3\n"), 3);
+    assert_eq!(parse_pids_events(""), 0);
+
+    assert_eq!(signal_name(6), Some(("SIGABRT", "aborted")));
+    assert_eq!(signal_name(42), None);
+
+    let mut settings = get_test_settings();
+    settings.ram_load = "250m".into();
+    settings.pids_limit = 64;
+    assert_eq!(Termination::OutOfMemory.describe(&settings), "Killed: out of memory (250m limit)");
+    assert_eq!(Termination::ProcessLimit.describe(&settings), "Stopped: too many processes (64 limit)");
+    assert_eq!(Termination::Signal(11).describe(&settings), "Killed by SIGSEGV: segmentation fault");
+    assert_eq!(Termination::Signal(42).describe(&settings), "Killed by signal 42");
+}
+
+#[test]
+fn test_fake_resource_usage() {
+    use commands::exec::ResourceUsage;
+
+    let usage = ResourceUsage { peak_memory: 1 << 20, user_time: Duration::from_millis(120), system_time: Duration::from_millis(5) };
+    let backend = FakeBackend::new(move |_, _| ExecResult { usage: Some(usage), ..output(0, "", "") });
+    let (compilation, execution, session) = run_fake("fn main() {}", "rust", &ExecOptions::default(), &get_test_settings(), &backend);
+    assert!(!session.steps[0].measure_usage, "Only the program should be measured");
+    assert!(session.steps[1].measure_usage);
+    assert_eq!(compilation.usage, None);
+    assert_eq!(execution.usage, Some(usage));
+}
+
+#[test]
+fn test_filter_emitted_code() {
+    use commands::exec::language::{ filter_asm, filter_llvm_ir, Emit, Language };
+
+    let asm = "\t.file\t\"main.c\"\n\t.intel_syntax noprefix\n\t.text\n\t.globl\tmain\n\t.type\tmain, @function\nmain:\n.LFB0:\n\tpush\trbp\n\tjmp\t.L2\n.L2:\n\t# a comment\n\tret\n.LFE0:\n\t.size\tmain, .-main\n";
+    assert_eq!(filter_asm(asm), "main:\n\tpush\trbp\n\tjmp\t.L2\n.L2:\n\tret\n");
+
+    let ir = "; ModuleID = 'main.3a1fbbbh-cgu.0'\nsource_filename = \"main\"\ntarget triple = \"x86_64-unknown-linux-gnu\"\n\ndefine i32 @main() #0 {\n  ret i32 0\n}\n\nattributes #0 = { nounwind }\n!0 = !{i32 7}\n";
+    assert_eq!(filter_llvm_ir(ir), "define i32 @main() #0 {\n  ret i32 0\n}\n\n");
+
+    let go = "main.main STEXT size=5 args=0x0 locals=0x0\n\t0x0000 00000 (/home/main.go:3)\tTEXT\tmain.main(SB), ABIInternal, $0-0\n\t0x0000 00000 (/home/main.go:4)\tRET\n\t0x0000 c3 cc cc cc                                      ....\n\trel 1+4 t=R_CALL runtime.morestack+0\n";
+    let filtered = commands::exec::Go.filter_emitted_code(go, Emit::Asm);
+    assert_eq!(filtered.lines().count(), 3);
+    assert!(!filtered.contains("c3 cc") && !filtered.contains("rel "));
+}
+
+#[test]
+fn test_fake_emit() {
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "rustc" => output(0, "\t.text\n_ZN4main4main17h0123456789abcdefE:\n\tret\n", ""),
+        "c++filt" => output(0, &step.stdin.as_ref().unwrap().replace("_ZN4main4main17h0123456789abcdefE", "main::main"), ""),
+        _ => panic!("The snippet should not run"),
+    });
+    let options = ExecOptions { emit: Some(commands::exec::language::Emit::Asm), opt_level: Some("2".into()), ..Default::default() };
+    let (compilation, execution, session) = run_fake("fn main() {}", "rust", &options, &get_test_settings(), &backend);
+    assert_eq!(session.steps.len(), 2);
+    let src = session.steps[0].command.last().unwrap();
+    assert_eq!(session.steps[0].command.join(" "), format!("rustc --crate-type=lib --emit=asm=- -Cllvm-args=-x86-asm-syntax=intel -Copt-level=2 {}", src));
+    assert_eq!(session.steps[1].stdin.as_deref(), Some("_ZN4main4main17h0123456789abcdefE:\n\tret\n"));
+    assert_eq!(compilation.stdout, "main::main:\n\tret\n");
+    assert_eq!(execution.exit_code, None);
+    assert_eq!(session.files[&std::path::PathBuf::from(src)], "fn main() {}", "Emitted code should not be wrapped");
+
+    // Go writes the listing to the standard error, and compiling it on its own misses the standard library
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "go" => output(0, "", "# command-line-arguments\nmain.main STEXT size=5 args=0x0 locals=0x0\n\t0x0000 00000 (/home/main.go:4)\tRET\n"),
+        _ => panic!("The snippet should not run"),
+    });
+    let options = ExecOptions { emit: Some(commands::exec::language::Emit::Asm), opt_level: Some("0".into()), ..Default::default() };
+    let (compilation, _, session) = run_fake("package main\nimport \"fmt\"\nfunc main() {\n\tfmt.Println(1)\n}", "go", &options, &get_test_settings(), &backend);
+    assert_eq!(session.steps.len(), 1);
+    let src = session.steps[0].command.last().unwrap();
+    assert_eq!(session.steps[0].command, vec!["go", "build", "-gcflags=-S\t-N\t-l", "-o", "/tmp/emit.out", src]);
+    assert!(compilation.stdout.contains("RET") && !compilation.stdout.contains("command-line-arguments"), "{}", compilation.stdout);
+    assert_eq!(compilation.stderr, "");
+
+    let mut lang_manager = LangManager::new();
+    lang_manager.set_language_available("Python".into(), true);
+    let lang = commands::exec::get_lang(&lang_manager, "python").unwrap();
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let user = serenity::model::id::UserId::from(123_456_u64);
+    let res = commands::exec::run_code(&get_test_settings(), &backend, vec!["print(1)".into()], lang, &options, user, None, None);
+    assert!(res.is_err(), "Python cannot emit assembly");
+    assert!(backend.sessions().is_empty());
+}
+
+#[test]
+fn test_fake_format() {
+    use commands::fmt::format_code;
+
+    let mut lang_manager = LangManager::new();
+    let languages = lang_manager.get_languages().clone();
+    for (_codes, boxed_lang) in languages {
+        lang_manager.set_language_available(boxed_lang.get_lang_name(), true);
+    }
+    let rust = commands::exec::get_lang(&lang_manager, "rust").unwrap();
+
+    let backend = FakeBackend::new(|_, step| {
+        assert_eq!(step.command, vec!["rustfmt".to_string(), "--edition".into(), "2021".into()]);
+        output(0, &step.stdin.as_ref().unwrap().replace("{", " {\n    ").replace("}", "\n}\n"), "")
+    });
+    let code = format_code(&get_test_settings(), &backend, &rust, "fn main(){println!(\"hi\");}").unwrap();
+    assert_eq!(code, "fn main() {\n    println!(\"hi\");\n}\n");
+    let sessions = backend.sessions();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].image, "rustacean-rust");
+    assert!(sessions[0].torn_down);
+
+    let backend = FakeBackend::new(|_, _| output(1, "", "error: expected item, found `let`"));
+    let e = format_code(&get_test_settings(), &backend, &rust, "let x = 1;").unwrap_err();
+    assert!(e.to_string().contains("expected item"), "{}", e);
+    assert!(backend.sessions()[0].torn_down);
+
+    let lua = commands::exec::get_lang(&lang_manager, "lua").unwrap();
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    assert!(format_code(&get_test_settings(), &backend, &lua, "print(1)").is_err(), "Lua has no formatter");
+    assert!(backend.sessions().is_empty());
+}
+
+#[test]
+fn test_format_reply() {
+    use commands::fmt::format_reply;
+    use serenity::model::id::UserId;
+
+    let reply = format_reply(UserId(42), "rust", "fn main() {}\n").unwrap();
+    assert_eq!(reply, "<@42>,\n```rust\nfn main() {}\n```");
+
+    // Shown as they are, these would end the code section or ping, so they are attached unchanged
+    assert_eq!(format_reply(UserId(42), "rust", "/// ```\n/// assert!(true);\n/// ```\nfn f() {}\n"), None);
+    assert_eq!(format_reply(UserId(42), "python", "print(\"@everyone\")\n"), None);
+    assert_eq!(format_reply(UserId(42), "python", "# @here\n"), None);
+    assert_eq!(format_reply(UserId(42), "python", &"x = 1\n".repeat(400)), None);
+}
+
+#[test]
+fn test_parse_diagnostics() {
+    use commands::lint::{parse_diagnostics, Diagnostic, Severity};
+
+    let gcc = "/home/main.c: In function 'main':\n/home/main.c:4:5: warning: unused variable 'x' [-Wunused-variable]\n/home/main.c:5:16: error: 'y' undeclared (first use in this function)\n/usr/include/stdio.h:12:1: note: declared here\n";
+    assert_eq!(parse_diagnostics(gcc, "main.c"), vec![
+        Diagnostic { line: 4, column: Some(5), severity: Severity::Warning, message: "unused variable 'x' [-Wunused-variable]".into() },
+        Diagnostic { line: 5, column: Some(16), severity: Severity::Error, message: "'y' undeclared (first use in this function)".into() },
+    ]);
+
+    let clippy = "/home/main.rs:2:9: warning[clippy::needless_return]: unneeded `return` statement\nwarning: 1 warning emitted\n";
+    assert_eq!(parse_diagnostics(clippy, "main.rs")[0].severity, Severity::Warning);
+    assert_eq!(parse_diagnostics(clippy, "main.rs").len(), 1);
+
+    let shellcheck = "/home/main.sh:1:6: note: Double quote to prevent globbing and word splitting. [SC2086]\n";
+    assert_eq!(parse_diagnostics(shellcheck, "main.sh")[0].severity, Severity::Note);
+
+    let eslint = "/home/main.js:3:1: 'foo' is not defined. [Error/no-undef]\n\n1 problem\n";
+    assert_eq!(parse_diagnostics(eslint, "main.js"), vec![
+        Diagnostic { line: 3, column: Some(1), severity: Severity::Error, message: "'foo' is not defined. (no-undef)".into() },
+    ]);
+
+    let ruff = "main.py:1:8: F401 [*] `os` imported but unused\nFound 1 error.\n";
+    assert_eq!(parse_diagnostics(ruff, "main.py"), vec![
+        Diagnostic { line: 1, column: Some(8), severity: Severity::Warning, message: "F401 [*] `os` imported but unused".into() },
+    ]);
+}
+
+#[test]
+fn test_fake_lint() {
+    use commands::lint::{lint_code, Severity};
+
+    let mut lang_manager = LangManager::new();
+    let languages = lang_manager.get_languages().clone();
+    for (_codes, boxed_lang) in languages {
+        lang_manager.set_language_available(boxed_lang.get_lang_name(), true);
+    }
+    let c = commands::exec::get_lang(&lang_manager, "c").unwrap();
+
+    // Snippets without a main function are wrapped in one, which moves them 3 lines down
+    let backend = FakeBackend::new(|session, step| {
+        assert_eq!(step.command[0], "gcc");
+        assert!(session.files.contains_key(&std::path::PathBuf::from("/home/main.c")));
+        output(1, "", "/home/main.c:3:1: warning: wrapper\n/home/main.c:5:16: error: 'y' undeclared\n")
+    });
+    let diagnostics = lint_code(&get_test_settings(), &backend, &c, "int x;\nprintf(\"%d\", y);", serenity::model::id::UserId(1)).unwrap();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, Some(16)));
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert!(backend.sessions()[0].torn_down);
+
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    assert!(lint_code(&get_test_settings(), &backend, &c, "int main() { return 0; }", serenity::model::id::UserId(1)).unwrap().is_empty());
+
+    let backend = FakeBackend::new(|_, _| output(1, "", "gcc: fatal: cannot execute 'cc1'"));
+    assert!(lint_code(&get_test_settings(), &backend, &c, "int main() { return 0; }", serenity::model::id::UserId(1)).is_err());
+
+    let lua = commands::exec::get_lang(&lang_manager, "lua").unwrap();
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    assert!(lint_code(&get_test_settings(), &backend, &lua, "print(1)", serenity::model::id::UserId(1)).is_err(), "Lua has no linter");
+    assert!(backend.sessions().is_empty());
+}
+
+#[test]
+fn test_fake_bench() {
+    use std::sync::atomic::{ AtomicUsize, Ordering };
+
+    let mut settings = get_test_settings();
+    settings.execution_timeout = 10;
+    settings.bench_max_runs = 5;
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "rustc" => output(0, "", ""),
+        "true" => ExecResult { duration: Duration::from_millis(100), ..output(0, "", "") },
+        _ => ExecResult { duration: Duration::from_millis(300), ..output(0, "test", "") },
+    });
+    let options = ExecOptions { bench: Some(50), args: vec!["foo".into()], ..Default::default() };
+    let (compilation, execution, session) = run_fake("print!(\"test\");", "rust", &options, &settings, &backend);
+    assert_eq!(compilation.exit_code, Some(0));
+    assert_eq!(execution.stdout, "test");
+    let bench = execution.bench.unwrap();
+    assert_eq!(bench.requested, 5, "The number of runs should be capped by the settings");
+    assert_eq!(bench.runs, vec![Duration::from_millis(200); 5], "The overhead should be subtracted");
+    assert_eq!(bench.stopped, None);
+    assert_eq!(session.steps.iter().filter(|step| step.command[0] == "rustc").count(), 1, "The snippet should be compiled once");
+    assert_eq!(session.steps.len(), 1 + 1 + 3 + 5);
+    assert!(session.steps[5..].iter().all(|step| step.command == session.steps[1].command));
+
+    // The first run and the benchmark share the execution timeout
+    settings.execution_timeout = 2;
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "rustc" | "true" => output(0, "", ""),
+        _ => ExecResult { duration: Duration::from_millis(500), ..output(0, "", "") },
+    });
+    let (_, execution, _) = run_fake("", "rust", &options, &settings, &backend);
+    let bench = execution.bench.unwrap();
+    assert_eq!(bench.runs.len(), 3);
+    assert_eq!(bench.stopped.as_deref(), Some("the execution timeout was reached"));
+
+    let runs = AtomicUsize::new(0);
+    let backend = FakeBackend::new(move |_, step| match step.command[0].as_str() {
+        "rustc" | "true" => output(0, "", ""),
+        _ if runs.fetch_add(1, Ordering::SeqCst) == 2 => output(101, "", "panicked"),
+        _ => output(0, "", ""),
+    });
+    let (_, execution, _) = run_fake("", "rust", &options, &settings, &backend);
+    let bench = execution.bench.unwrap();
+    assert_eq!(bench.runs.len(), 1);
+    assert_eq!(bench.stopped.as_deref(), Some("run 2 exited with code 101"));
+
+    // Failed programs are not benchmarked
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "rustc" => output(0, "", ""),
+        _ => output(1, "", ""),
+    });
+    let (_, execution, session) = run_fake("", "rust", &options, &settings, &backend);
+    assert!(execution.bench.is_none());
+    assert_eq!(session.steps.len(), 2);
+}
+
+#[test]
+fn test_check_flags() {
+    let lang_manager = LangManager::new();
+    let check = |lang: &str, flags: &[&str]| {
+        let lang = lang_manager.get(lang).unwrap();
+        let flags: Vec<String> = flags.iter().map(|flag| flag.to_string()).collect();
+        commands::exec::check_flags(&lang, &flags)
+    };
+
+    assert!(check("c", &["-O2", "-Wall", "-std=c99"]).is_ok());
+    assert!(check("rust", &["--edition=2021", "-Copt-level=3"]).is_ok());
+    assert!(check("cs", &["-langversion:7.3"]).is_ok());
+    assert!(check("c", &[]).is_ok());
+    assert!(check("c", &["-fplugin=evil.so"]).is_err());
+    assert!(check("c", &["-std="]).is_err());
+    assert!(check("c", &["-std=c99;rm"]).is_err());
+    assert!(check("c", &["-O2 -o /tmp/x"]).is_err());
+    assert!(check("rust", &["--edition"]).is_err());
+    assert!(check("sh", &["-x"]).is_err());
+}
+
+#[test]
+fn test_fake_compiler_flags() {
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let options = ExecOptions {
+        flags: vec!["-O2".into(), "-Wall".into()],
+        ..Default::default()
+    };
+    let (compilation, execution, session) = run_fake("puts(\"hi\");", "c", &options, &get_test_settings(), &backend);
+    let src = session.steps[0].command[1].clone();
+    assert_eq!(session.steps[0].command, vec!["gcc".to_string(), src.clone(), "-o".into(), format!("{}.out", src), "-O2".into(), "-Wall".into()]);
+    assert_eq!(compilation.command, format!("gcc {} -o {}.out -O2 -Wall", src, src));
+    assert_eq!(execution.command, format!("{}.out", src));
+
+    // Interpreters take the flags before the script
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let options = ExecOptions {
+        flags: vec!["-O".into()],
+        args: vec!["foo".into()],
+        ..Default::default()
+    };
+    let (compilation, execution, session) = run_fake("print(1)", "python", &options, &get_test_settings(), &backend);
+    assert_eq!(session.steps[0].command[..2], ["python3".to_string(), "-O".into()]);
+    assert_eq!(session.steps[0].command[3], "foo");
+    assert!(compilation.command.is_empty());
+    assert!(execution.command.starts_with("python3 -O /home/"));
+
+    // Disallowed flags are rejected before anything runs
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let lang_manager = LangManager::new();
+    let lang = lang_manager.get("c").unwrap();
+    let options = ExecOptions {
+        flags: vec!["-fplugin=evil.so".into()],
+        ..Default::default()
+    };
+    let user = serenity::model::id::UserId::from(123_456_u64);
+    let res = commands::exec::run_code(&get_test_settings(), &backend, vec!["".into()], lang, &options, user, None, None);
+    assert!(res.is_err());
+    assert!(backend.sessions().is_empty());
+}
+
+#[test]
+fn test_code_blocks() {
+    let blocks = commands::exec::get_code_blocks("~exec\n```py\nprint(input())\n```\nwith\n```stdin\nhello\n```");
+    assert_eq!(blocks, vec![
+        CodeBlock { tag: "py".into(), filename: None, code: "print(input())\n".into() },
+        CodeBlock { tag: "stdin".into(), filename: None, code: "hello\n".into() },
+    ]);
+    assert!(!blocks[0].is_stdin());
+    assert!(blocks[1].is_stdin());
+
+    assert!(commands::exec::get_code_blocks("~exec print(1)").is_empty());
+
+    let blocks = commands::exec::get_code_blocks("~exec ```print(\"hi\")``` ```x = 1\nprint(x)\n``` ```\nputs 1\n```");
+    assert_eq!(blocks, vec![
+        CodeBlock { tag: "".into(), filename: None, code: "print(\"hi\")".into() },
+        CodeBlock { tag: "".into(), filename: None, code: "x = 1\nprint(x)\n".into() },
+        CodeBlock { tag: "".into(), filename: None, code: "puts 1\n".into() },
+    ]);
+
+    let blocks = commands::exec::get_code_blocks("~exec\n```c main.c\nint main() {}\n```\n```c util.h\nint f();\n```");
+    assert_eq!(blocks[0].filename, Some("main.c".to_string()));
+    assert_eq!(blocks[1].tag, "c");
+    assert_eq!(blocks[1].filename, Some("util.h".to_string()));
+}
+
+#[test]
+fn test_source_files() {
+    let get_files = |content: &str| commands::exec::get_source_files(commands::exec::get_code_blocks(content));
+
+    let files = get_files("```c\nputs(\"hi\");\n```").unwrap();
+    assert_eq!(files, vec![SourceFile { name: None, code: "puts(\"hi\");\n".into() }]);
+
+    let files = get_files("```c main.c\nint main() {}\n``` ```c util.c\nint f() {}\n```").unwrap();
+    assert_eq!(files.len(), 2);
+    assert_eq!(files[1].name, Some("util.c".to_string()));
+
+    assert!(get_files("```c main.c\n``` ```c\n```").is_err());
+    assert!(get_files("```c main.c\n``` ```c main.c\n```").is_err());
+    assert!(get_files("```c ../main.c\n```").is_err());
+    assert!(get_files("```c .bashrc\n```").is_err());
+}
+
+#[test]
+fn test_fake_multi_file() {
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let files = vec![
+        SourceFile { name: Some("main.c".into()), code: "#include \"util.h\"\nint main() { return f(); }".into() },
+        SourceFile { name: Some("util.h".into()), code: "int f();".into() },
+        SourceFile { name: Some("util.c".into()), code: "int f() { return 0; }".into() },
+    ];
+    let (_, _, session) = run_fake_files(files, "c", &ExecOptions::default(), &get_test_settings(), &backend);
+
+    assert_eq!(session.files[&std::path::PathBuf::from("/home/util.h")], "int f();");
+    assert_eq!(session.files[&std::path::PathBuf::from("/home/util.c")], "int f() { return 0; }");
+    assert!(session.files[&std::path::PathBuf::from("/home/main.c")].starts_with("#include \"util.h\""));
+    assert_eq!(
+        session.steps[0].command,
+        vec!["gcc", "/home/main.c", "/home/util.c", "-o", "/home/main.c.out"]
+    );
+    assert_eq!(session.steps[1].command, vec!["/home/main.c.out"]);
+
+    // Only the main file is wrapped in a class
+    let files = vec![
+        SourceFile { name: Some("Main.java".into()), code: "System.out.println(Util.NAME);".into() },
+        SourceFile { name: Some("Util.java".into()), code: "class Util { static String NAME = \"util\"; }".into() },
+    ];
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let (_, _, session) = run_fake_files(files, "java", &ExecOptions::default(), &get_test_settings(), &backend);
+    assert!(session.files[&std::path::PathBuf::from("/home/Main.java")].contains("class Main"));
+    assert!(session.files[&std::path::PathBuf::from("/home/Util.java")].starts_with("class Util"));
+    assert_eq!(session.steps[0].command, vec!["javac", "/home/Main.java", "/home/Util.java"]);
+}
+
+#[test]
+fn test_diagnostics_by_file() {
+    let output = "/home/main.c:3:5: error: expected ';'\n/home/util.c:1:1: warning: unused\n/home/util.c:2:1: error: oops\nutil.cs(4,2): error CS1002";
+    let names = vec!["main.c".to_string(), "util.c".to_string(), "util.cs".to_string(), "ain.c".to_string()];
+    assert_eq!(commands::exec::count_diagnostics_by_file(output, &names), vec![
+        ("main.c".to_string(), 1),
+        ("util.c".to_string(), 2),
+        ("util.cs".to_string(), 1),
+        ("ain.c".to_string(), 0),
+    ]);
+}
+
+fn wait_for_pool(pool: &ContainerPool, image: &str, idle: usize) {
+    for _ in 0..200 {
+        let status = pool.status()[image].clone();
+        if status.idle == idle && status.starting == 0 {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    panic!("Pool for {} never reached {} idle sessions: {:?}", image, idle, pool.status()[image]);
+}
+
+#[test]
+fn test_container_pool() {
+    let backend = Arc::new(FakeBackend::new(|_, _| output(0, "", "")));
+    let mut sizes = HashMap::new();
+    sizes.insert("rustacean-python".to_string(), 2);
+    let pool = ContainerPool::new(backend.clone(), get_test_settings(), sizes);
+
+    pool.fill();
+    wait_for_pool(&pool, "rustacean-python", 2);
+    assert_eq!(backend.sessions().len(), 2);
+
+    // Pooled sessions are handed out once and replaced in the background
+    let session = pool.start_session(&get_test_settings(), "rustacean-python").unwrap();
+    assert!(session == "fake-0" || session == "fake-1");
+    wait_for_pool(&pool, "rustacean-python", 2);
+    assert_eq!(backend.sessions().len(), 3);
+    pool.teardown(&session);
+    assert_eq!(backend.sessions().iter().filter(|s| s.torn_down).count(), 1);
+
+    // Images without a pool are started on demand
+    let session = pool.start_session(&get_test_settings(), "rustacean-lua").unwrap();
+    assert_eq!(session, "fake-3");
+    assert_eq!(pool.status().len(), 1);
+
+    let status = &pool.status()["rustacean-python"];
+    assert_eq!((status.size, status.hits, status.misses), (2, 1, 0));
+
+    pool.drain();
+    assert_eq!(pool.status()["rustacean-python"].idle, 0);
+    assert_eq!(backend.sessions().iter().filter(|s| s.torn_down).count(), 3);
+
+    // Drained pools are not refilled
+    let session = pool.start_session(&get_test_settings(), "rustacean-python").unwrap();
+    assert_eq!(session, "fake-4");
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(backend.sessions().len(), 5);
+    assert_eq!(pool.status()["rustacean-python"].starting, 0);
+}
+
+#[test]
+fn test_pool_waiting_for_image() {
+    let backend = Arc::new(FakeBackend::new(|_, _| output(0, "", "")));
+    let mut sizes = HashMap::new();
+    sizes.insert("rustacean-python".to_string(), 2);
+    let pool = ContainerPool::new(backend.clone(), get_test_settings(), sizes);
+
+    // Nothing is started for an image being built, executions start their own session meanwhile
+    pool.wait_for_image("rustacean-python");
+    pool.fill();
+    let session = pool.start_session(&get_test_settings(), "rustacean-python").unwrap();
+    assert_eq!(session, "fake-0");
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(backend.sessions().len(), 1);
+    assert_eq!(pool.status()["rustacean-python"].misses, 1);
+
+    pool.image_ready("rustacean-python");
+    wait_for_pool(&pool, "rustacean-python", 2);
+    assert_eq!(backend.sessions().len(), 3);
+}
+
+#[test]
+fn test_lang_manager_pool() {
+    let mut lang_manager = LangManager::new();
+    lang_manager.set_backend(Arc::new(FakeBackend::new(|_, _| output(0, "", ""))));
+    lang_manager.set_language_available("Python".into(), true);
+    lang_manager.set_language_available("Lua".into(), true);
+    lang_manager.set_language_available("Rust".into(), true);
+
+    let mut settings = get_test_settings();
+    lang_manager.init_pool(&settings);
+    assert!(lang_manager.get_pool().is_none());
+
+    settings.pool_size = 1;
+    settings.pool_sizes.insert("py".into(), 3);
+    settings.pool_sizes.insert("rust".into(), 0);
+    lang_manager.init_pool(&settings);
+    let pool = lang_manager.get_pool().unwrap();
+    let mut sizes: Vec<(String, usize)> = pool.status().into_iter().map(|(image, status)| (image, status.size)).collect();
+    sizes.sort();
+    assert_eq!(sizes, vec![("rustacean-lua".to_string(), 1), ("rustacean-python".into(), 3)]);
+    wait_for_pool(&pool, "rustacean-python", 3);
+    wait_for_pool(&pool, "rustacean-lua", 1);
+
+    // Re-initializing drops the previous pool instead of stacking a new one on top of it
+    lang_manager.init_pool(&settings);
+    assert_eq!(pool.status()["rustacean-python"].idle, 0);
+    let new_pool = lang_manager.get_pool().unwrap();
+    assert!(Arc::ptr_eq(&new_pool.get_backend(), &pool.get_backend()));
+    wait_for_pool(&new_pool, "rustacean-python", 3);
+}
+
+#[test]
+fn test_build_inputs() {
+    use crate::lang_manager::{ get_copied_sources, hash_build_inputs };
+
+    assert_eq!(
+        get_copied_sources(
+            "FROM rust:latest AS build\n\
+             COPY images/packages/rust /opt/packages\n\
+             copy --chown=1000 a.txt b/ /opt/\n\
+             ADD [\"c.json\", \"/opt/c.json\"]\n\
+             ADD https://example.com/d.tar.gz /opt/\n\
+             COPY --from=build /usr/local/bin/e /usr/local/bin/e\n\
+             RUN echo COPY f /g && \\\n    COPY=1 true\n"
+        ),
+        vec!["images/packages/rust", "a.txt", "b/", "c.json"]
+    );
+
+    let dir = std::env::temp_dir().join(format!("rustacean-test-build-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("packages/nested")).unwrap();
+    std::fs::write(dir.join("Dockerfile"), "FROM ubuntu:latest\nCOPY packages /opt/packages\nCOPY lint*.json /opt/\n").unwrap();
+    std::fs::write(dir.join("packages/Cargo.toml"), "[dependencies]\n").unwrap();
+    std::fs::write(dir.join("packages/nested/list.txt"), "rand\n").unwrap();
+    std::fs::write(dir.join("lint.json"), "{}").unwrap();
+    std::fs::write(dir.join("unused.txt"), "").unwrap();
+    let hash = || hash_build_inputs(&dir.join("Dockerfile"), &dir).unwrap();
+
+    let first = hash();
+    assert_eq!(first.len(), 40);
+    assert_eq!(hash(), first);
+    std::fs::write(dir.join("unused.txt"), "not copied").unwrap();
+    assert_eq!(hash(), first, "Files the Dockerfile does not copy are left out");
+
+    std::fs::write(dir.join("packages/nested/list.txt"), "rand\nregex\n").unwrap();
+    let second = hash();
+    assert_ne!(second, first, "Files of copied directories are hashed");
+    std::fs::write(dir.join("lint.json"), "{\"rules\": {}}").unwrap();
+    let third = hash();
+    assert_ne!(third, second, "Files matched by a pattern are hashed");
+    std::fs::rename(dir.join("packages/Cargo.toml"), dir.join("packages/Cargo.lock")).unwrap();
+    assert_ne!(hash(), third, "Renaming a copied file changes the hash");
+
+    std::fs::remove_dir_all(dir.join("packages")).unwrap();
+    assert!(hash_build_inputs(&dir.join("Dockerfile"), &dir).is_err(), "Missing sources fail like docker build");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reload_checked_state() {
+    let dir = std::env::temp_dir().join(format!("rustacean-test-reload-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |contents: &str| std::fs::write(dir.join("zig.toml"), contents).unwrap();
+    write("name = \"Zig\"\naliases = [\"zig\"]\nextension = \".zig\"\nversion = \"zig version\"\n");
+
+    let backend: Arc<FakeBackend> = Arc::new(FakeBackend::new(|_, _| output(0, "", "")));
+    let mut previous = LangManager::new();
+    previous.load_toml_languages(&dir);
+    previous.set_backend(backend.clone());
+    previous.set_language_available("Python".into(), true);
+    previous.set_language_available("Zig".into(), true);
+    let mut settings = get_test_settings();
+    settings.pool_size = 1;
+    previous.init_pool(&settings);
+    let pool = previous.get_pool().unwrap();
+    wait_for_pool(&pool, "rustacean-python", 1);
+
+    // Only the languages which were never checked or whose definition changed are checked again
+    write("name = \"Zig\"\naliases = [\"zig\"]\nextension = \".zig\"\nversion = \"zig version --verbose\"\n");
+    let mut lang_manager = LangManager::new();
+    lang_manager.load_toml_languages(&dir);
+    let changed = lang_manager.take_checked_state(&previous);
+    assert!(changed.contains(&"Zig".to_string()));
+    assert!(changed.contains(&"Rust".to_string()));
+    assert!(!changed.contains(&"Python".to_string()));
+    assert!(lang_manager.is_language_available(&lang_manager.get("py").unwrap()));
+    assert!(!lang_manager.is_language_available(&lang_manager.get("zig").unwrap()));
+
+    // The new pool starts its sessions on the backend of the previous one, not on top of it
+    lang_manager.init_pool(&settings);
+    let new_pool = lang_manager.get_pool().unwrap();
+    assert!(Arc::ptr_eq(&new_pool.get_backend(), &pool.get_backend()));
+    assert_eq!(new_pool.status().len(), 1);
+
+    // Executions still holding the previous pool are served without refilling it
+    pool.drain();
+    pool.start_session(&settings, "rustacean-python").unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    let status = &pool.status()["rustacean-python"];
+    assert_eq!((status.size, status.idle, status.starting, status.misses), (0, 0, 0, 1));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_bounded_buffer() {
+    let mut buffer = BoundedBuffer::new(0);
+    assert!(buffer.push(&[b'a'; 100_000]));
+    assert_eq!(buffer.into_parts().1, 0);
+
+    let mut buffer = BoundedBuffer::new(5);
+    assert!(buffer.push(b"abc"));
+    assert!(!buffer.push(b"defgh"));
+    assert!(!buffer.push(b"ij"));
+    assert_eq!(buffer.into_parts(), (b"abcde".to_vec(), 5));
+
+    // Characters cut by the limit are dropped along with the rest of the output
+    let mut buffer = BoundedBuffer::new(4);
+    assert!(!buffer.push("abcé".as_bytes()));
+    assert_eq!(buffer.into_parts(), (b"abc".to_vec(), 2));
+
+    let mut buffer = BoundedBuffer::new(4);
+    assert!(!buffer.push(b"ab\xffcd"));
+    assert_eq!(buffer.into_parts(), (b"ab\xffc".to_vec(), 1));
+}
+
+#[test]
+fn test_fake_output_limit() {
+    let mut settings = get_test_settings();
+    settings.output_limit = 10;
+
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "gcc" => output(0, "", "warning"),
+        _ => output(0, &"x".repeat(100), "done"),
+    });
+    let (compilation, execution, session) = run_fake("for (;;) putchar('x');", "c", &ExecOptions::default(), &settings, &backend);
+
+    assert!(session.steps.iter().all(|step| step.output_limit == 10));
+    assert!(!compilation.is_truncated());
+    assert_eq!(execution.stdout, "x".repeat(10));
+    assert_eq!((execution.stdout_dropped, execution.stderr_dropped), (90, 0));
+    assert_eq!(execution.stderr, "done");
+    assert_eq!(execution.exit_code, None);
+
+    // Programs are not run when their compiler output was cut
+    let backend = FakeBackend::new(|_, _| output(0, "", "warning: unused variable"));
+    let (compilation, execution, session) = run_fake("int x;", "c", &ExecOptions::default(), &settings, &backend);
+    assert_eq!(compilation.stderr, "warning: u");
+    assert_eq!(compilation.stderr_dropped, 14);
+    assert_eq!(compilation.exit_code, None);
+    assert_eq!(session.steps.len(), 1);
+    assert!(!execution.is_truncated());
+}
+
+#[test]
+#[cfg_attr(not(unix), ignore)]
+fn test_run_command_output_limit() {
+    use commands::exec::backend::run_command;
+
+    let mut cmd = std::process::Command::new("sh");
+    cmd.args(["-c", "while true; do echo spam; done"]);
+    let res = run_command(cmd, Vec::new(), 10, 1000, None).unwrap();
+    assert!(!res.timed_out);
+    assert_eq!(res.exit_code, None);
+    assert_eq!(res.stdout.len(), 1000);
+    assert!(res.stdout_dropped > 0);
+
+    let mut cmd = std::process::Command::new("sh");
+    cmd.args(["-c", "cat; echo err >&2; exit 3"]);
+    let res = run_command(cmd, b"input".to_vec(), 10, 1000, None).unwrap();
+    assert_eq!(res.exit_code, Some(3));
+    assert_eq!(res.stdout, "input");
+    assert_eq!(res.stderr, "err\n");
+    assert!(!res.is_truncated());
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let mut cmd = std::process::Command::new("sh");
+    cmd.args(["-c", "echo one; sleep 0.1; echo two >&2; echo three"]);
+    let res = run_command(cmd, Vec::new(), 10, 1000, Some(sender)).unwrap();
+    assert_eq!(res.stdout, "one\nthree\n");
+    assert_eq!(receiver.iter().flatten().collect::<Vec<u8>>(), b"one\nthree\n");
+
+    let mut cmd = std::process::Command::new("sh");
+    cmd.args(["-c", "sleep 5"]);
+    let res = run_command(cmd, Vec::new(), 1, 1000, None).unwrap();
+    assert!(res.timed_out);
+    assert!(res.duration >= Duration::from_secs(1) && res.duration < Duration::from_secs(2), "{:?}", res.duration);
+
+    // Durations are not rounded up to a coarse polling interval
+    let mut cmd = std::process::Command::new("sh");
+    cmd.args(["-c", "sleep 0.05"]);
+    let res = run_command(cmd, Vec::new(), 10, 1000, None).unwrap();
+    assert!(res.duration >= Duration::from_millis(50), "{:?}", res.duration);
+    assert!(res.duration < Duration::from_millis(150), "{:?}", res.duration);
+}
+
+#[test]
+fn test_stream_view() {
+    use commands::exec::{format_stream_view, STREAM_VIEW_LENGTH};
+
+    assert_eq!(format_stream_view("<@1>,\nRunning code snippet...", b"hi ```@everyone"), "<@1>,\nRunning code snippet...\n```\nhi @ everyone\n```");
+
+    let output = format!("{}{}", "a".repeat(10), "é".repeat(STREAM_VIEW_LENGTH));
+    let view = format_stream_view("", output.as_bytes());
+    assert_eq!(view, format!("\n```\n{}\n```", "é".repeat(STREAM_VIEW_LENGTH)));
+}
+
+#[test]
+fn test_fake_invalid_utf8() {
+    let backend = FakeBackend::new(|_, step| match step.command[0].as_str() {
+        "gcc" => output(0, "", ""),
+        _ => ExecResult { stdout_raw: b"ok \xff\n".to_vec(), ..output(0, "", "") },
+    });
+    let (compilation, execution, _) = run_fake("putchar(255);", "c", &ExecOptions::default(), &get_test_settings(), &backend);
+
+    assert!(!compilation.invalid_utf8);
+    assert!(execution.invalid_utf8);
+    assert_eq!(execution.stdout, "ok \u{FFFD}\n");
+    assert_eq!(execution.stdout_raw, b"ok \xff\n");
+    assert_eq!(execution.exit_code, Some(0));
+}
+
+#[test]
+fn test_hexdump() {
+    use commands::exec::format_hexdump;
+
+    assert_eq!(format_hexdump(b""), "");
+    assert_eq!(
+        format_hexdump(b"Hello,\n\xff`@ world"),
+        "00000000  48 65 6c 6c 6f 2c 0a ff  |Hello,..|\n\
+         00000008  60 40 20 77 6f 72 6c 64  |.. world|\n"
+    );
+    assert_eq!(format_hexdump(b"abc"), "00000000  61 62 63                 |abc|\n");
+}
+
+#[test]
+fn test_packages() {
+    let lang_manager = LangManager::new();
+    let rust = lang_manager.get("rust").unwrap();
+    let crates = rust.get_packages();
+    assert!(crates.contains(&("rand".to_string(), "0.8.5".to_string())));
+    assert!(crates.iter().any(|(name, version)| name == "serde" && version.starts_with("1.")));
+
+    let python = lang_manager.get("python").unwrap();
+    assert!(python.get_packages().iter().any(|(name, _)| name == "numpy"));
+    assert!(lang_manager.get("c").unwrap().get_packages().is_empty());
+
+    let path = std::env::temp_dir().join("rustacean-test-packages.txt");
+    std::fs::write(&path, "# comment\nlodash@4.17.21\n\n@types/node@20.1.0\nleft-pad\n").unwrap();
+    let packages = commands::exec::language::read_package_list(path.to_str().unwrap(), "@");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(packages, vec![
+        ("lodash".to_string(), "4.17.21".to_string()),
+        ("@types/node".into(), "20.1.0".into()),
+        ("left-pad".into(), "*".into()),
+    ]);
+}
+
+#[test]
+fn test_fake_packages() {
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let (_, _, session) = run_fake("let x: u8 = rand::random();\nprintln!(\"{}\", x);", "rust", &ExecOptions::default(), &get_test_settings(), &backend);
+    assert_eq!(session.steps[0].command[0], "rustc-packages");
+    assert_eq!(session.steps[0].command[1], "--edition=2018", "Snippets with and without packages share their edition");
+
+    // The edition given by the user replaces the default one
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let options = ExecOptions { flags: vec!["--edition=2021".into()], ..Default::default() };
+    let (_, _, session) = run_fake("let x: u8 = rand::random();\nprintln!(\"{}\", x);", "rust", &options, &get_test_settings(), &backend);
+    let editions: Vec<&String> = session.steps[0].command.iter().filter(|arg| arg.starts_with("--edition")).collect();
+    assert_eq!(editions, vec!["--edition=2021"]);
+
+    let backend = FakeBackend::new(|_, _| output(0, "", ""));
+    let (_, _, session) = run_fake("let operand = 1;\nprintln!(\"{}\", operand);", "rust", &ExecOptions::default(), &get_test_settings(), &backend);
+    assert_eq!(session.steps[0].command[..2], ["rustc".to_string(), "--edition=2018".into()]);
+}
+
+#[test]
+fn test_output_attachments() {
+    use commands::exec::Attachments;
+
+    let mut attachments = Attachments::new(4096);
+    let (label, text, _) = attachments.output_field("Standard output", "short".into(), b"short", 0, "stdout.txt");
+    assert_eq!(label, "Standard output");
+    assert_eq!(text, "```\nshort```");
+    let (label, _, _) = attachments.output_field("Standard output", "short".into(), b"short", 10, "stdout.txt");
+    assert_eq!(label, "Standard output (truncated)");
+    assert!(attachments.files.is_empty());
+
+    let long = "x".repeat(3000);
+    let (label, text, _) = attachments.output_field("Error output", long.clone(), long.as_bytes(), 0, "stderr.txt");
+    assert_eq!(label, "Error output (truncated, full output in stderr.txt)");
+    assert_eq!(text.len(), 1024);
+    assert_eq!(attachments.files, vec![("stderr.txt".to_string(), long.clone().into_bytes())]);
+
+    let mut attachments = Attachments::new(100);
+    let (label, _, _) = attachments.output_field("Standard output", long.clone(), long.as_bytes(), 0, "stdout.txt");
+    assert_eq!(label, "Standard output (truncated, first 100 bytes in stdout.txt)");
+    assert_eq!(attachments.files[0].1.len(), 100);
+
+    let mut attachments = Attachments::new(0);
+    let (label, _, _) = attachments.output_field("Standard output", long.clone(), long.as_bytes(), 0, "stdout.txt");
+    assert_eq!(label, "Standard output (truncated)");
+    assert!(attachments.files.is_empty());
+}
+
+#[test]
+fn test_toml_languages() {
+    use std::path::PathBuf;
+    use commands::exec::{ load_toml_languages, TomlLanguage };
+
+    let mut lang_manager = LangManager::new();
+    for (code, name) in [("lua", "Lua"), ("rb", "Ruby"), ("php", "PHP"), ("hs", "Haskell"), ("sh", "Shell"), ("julia", "Julia")].iter() {
+        assert_eq!(lang_manager.get(code).map(|lang| lang.get_lang_name()).as_deref(), Some(*name));
+    }
+    let php = lang_manager.get("php").unwrap();
+    assert_eq!(php.get_image_name(), "rustacean-php");
+    assert_eq!(php.pre_process_code("echo 1;", &PathBuf::from("/home/a.php")).as_deref(), Some("<?php\r\necho 1;"));
+    assert_eq!(php.pre_process_code("<?php echo 1;", &PathBuf::from("/home/a.php")), None);
+    let haskell = lang_manager.get("haskell").unwrap();
+    assert_eq!(
+        haskell.get_compiler_command(&PathBuf::from("/home/a.hs"), &PathBuf::from("/home/a.hs.out")).as_deref(),
+        Some("ghc -o /home/a.hs.out /home/a.hs")
+    );
+    assert_eq!(lang_manager.get("sh").unwrap().get_linter_command(&PathBuf::from("/home/a.sh")).as_deref(), Some("shellcheck -f gcc -s sh /home/a.sh"));
+
+    let backend = FakeBackend::new(|_, _| output(0, "1", ""));
+    let (_, execution, session) = run_fake("print(1)", "lua", &ExecOptions::default(), &get_test_settings(), &backend);
+    assert_eq!(session.steps.len(), 1);
+    assert_eq!(session.steps[0].command[0], "lua5.3");
+    assert_eq!(execution.stdout, "1");
+
+    let dir = std::env::temp_dir().join(format!("rustacean-test-languages-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let write = |name: &str, contents: &str| std::fs::write(dir.join(name), contents).unwrap();
+    write("a.toml", "name = \"Zig\"\naliases = [\"zig\"]\nextension = \".zig\"\nversion = \"zig version\"\ncompile = \"zig build-exe {srcs} -femit-bin={out}\"\nallowed_flags = [\"-O\"]\n");
+    write("b.toml", "name = \"Moon\"\naliases = [\"lua\"]\nextension = \".moon\"\nversion = \"moon -v\"\n");
+    write("c.toml", "name = \"Bad\"\naliases = [\"bad\"]\nextension = \"bad\"\nversion = \"bad\"\n");
+    write("d.toml", "name = \"Bad\"\naliases = [\"bad\"]\nextension = \".bad\"\nversion = \"bad\"\n[wrap]\nunless = \"(\"\ntemplate = \"{code}\"\n");
+    write("e.toml", "name = \"Bad\"\naliases = [\"bad\"]\nextension = \".bad\"\nversion = \"bad\"\ncompiler = \"bad\"\n");
+    write("notes.txt", "not a language");
+
+    let langs = load_toml_languages(&dir);
+    assert_eq!(langs.len(), 5);
+    assert!(langs[0].is_ok() && langs[1].is_ok());
+    for lang in langs[2..].iter() {
+        let e = lang.as_ref().unwrap_err().to_string();
+        assert!(e.contains(".toml: "), "Errors should name the file: {}", e);
+    }
+    assert!(TomlLanguage::load(&dir.join("missing.toml")).is_err());
+
+    lang_manager.load_toml_languages(&dir);
+    assert_eq!(lang_manager.get("lua").unwrap().get_lang_name(), "Lua", "Aliases cannot be taken over");
+    let zig = lang_manager.get("zig").unwrap();
+    assert_eq!(zig.get_image_name(), "rustacean-zig");
+    assert_eq!(zig.get_allowed_flags(), vec!["-O".to_string()]);
+    assert_eq!(
+        zig.get_compiler_command_for_files(&[PathBuf::from("/home/main.zig"), PathBuf::from("/home/util.zig"), PathBuf::from("/home/data.txt")], &PathBuf::from("/home/main.zig.out")).as_deref(),
+        Some("zig build-exe /home/main.zig /home/util.zig -femit-bin=/home/main.zig.out")
+    );
+    assert_eq!(zig.get_execution_command(&PathBuf::from("/home/main.zig.out")), "/home/main.zig.out");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_lang_by_filename() {
+    use commands::exec::get_lang_by_filename;
+
+    let mut lang_manager = LangManager::new();
+    assert!(get_lang_by_filename(&lang_manager, "main.rs").is_err(), "Unavailable languages should be refused");
+
+    let languages = lang_manager.get_languages().clone();
+    for (_codes, boxed_lang) in languages {
+        lang_manager.set_language_available(boxed_lang.get_lang_name(), true);
+    }
+    assert_eq!(get_lang_by_filename(&lang_manager, "main.rs").unwrap().get_lang_name(), "Rust");
+    assert_eq!(get_lang_by_filename(&lang_manager, "solution.PY").unwrap().get_lang_name(), "Python");
+    assert_eq!(get_lang_by_filename(&lang_manager, "my.test.cpp").unwrap().get_lang_name(), "Cpp");
+    match get_lang_by_filename(&lang_manager, "hello.asm") {
+        Err(e) => assert!(e.to_string().contains(" or "), "{}", e),
+        Ok(lang) => panic!("`.asm` should be ambiguous, got {}", lang.get_lang_name()),
+    }
+    assert!(get_lang_by_filename(&lang_manager, "Makefile").is_err());
+    assert!(get_lang_by_filename(&lang_manager, "notes.txt").is_err());
+}
+
+#[test]
+fn test_rejected_source_files() {
+    use commands::exec::{ check_source_file, format_rejected_files };
+
+    let block = check_source_file("main.c", Some(Ok(b"int main() {}".to_vec())), 100).unwrap();
+    assert_eq!((block.filename.as_deref(), block.code.as_str()), (Some("main.c"), "int main() {}"));
+
+    let rejected: Vec<(String, String)> = vec![
+        ("big.c", None),
+        ("lie.c", Some(Ok(vec![b'x'; 101]))),
+        ("logo.png", Some(Ok(vec![0x89, 0x50, 0xff, 0xfe]))),
+        ("lost.h", Some(Err("timed out".to_string()))),
+    ]
+    .into_iter()
+    .map(|(name, data)| (name.to_string(), check_source_file(name, data, 100).unwrap_err()))
+    .collect();
+    assert_eq!(rejected[0].1, "too large, source files are limited to 100 bytes");
+    assert_eq!(rejected[1].1, rejected[0].1);
+    assert_eq!(rejected[2].1, "not a text file");
+    assert_eq!(rejected[3].1, "could not be downloaded (timed out)");
+
+    // Each refused file is named along with its reason
+    let msg = format_rejected_files(&rejected);
+    assert!(msg.starts_with("Cannot run 4 of the uploaded files:\n"), "{}", msg);
+    for (name, reason) in rejected.iter() {
+        assert!(msg.contains(&format!("`{}`: {}", name, reason)), "{}", msg);
+    }
+    assert_eq!(format_rejected_files(&rejected[2..3]), "Cannot run `logo.png`: not a text file");
+}
+
+#[test]
+fn test_detect_language() {
+    use commands::exec::detect::{ detect_language, Confidence };
+
+    let detect = |code: &str| detect_language(code).map(|detection| (detection.lang_code, detection.confidence));
+    assert_eq!(detect("#!/usr/bin/env python3\nx = 1"), Some(("python", Confidence::High)));
+    assert_eq!(detect("#!/bin/bash -e\nls"), Some(("shell", Confidence::High)));
+    assert_eq!(detect("#!/usr/bin/env -S deno run\n"), Some(("typescript", Confidence::High)));
+    assert_eq!(detect("fn main() {\n    let mut v = vec![1, 2];\n    println!(\"{:?}\", v);\n}"), Some(("rust", Confidence::High)));
+    assert_eq!(detect("#include <iostream>\nint main() {\n    std::cout << 1 << std::endl;\n}"), Some(("cpp", Confidence::High)));
+    assert_eq!(detect("#include <stdio.h>\nint main() {\n    printf(\"%d\", 1);\n}"), Some(("c", Confidence::High)));
+    assert_eq!(detect("def square(x):\n    return x * x\n\nprint(square(3))"), Some(("python", Confidence::High)));
+    assert_eq!(detect("package main\nimport \"fmt\"\nfunc main() {\n    fmt.Println(1)\n}"), Some(("go", Confidence::High)));
+    assert_eq!(detect("const xs = [1, 2].map(x => x * 2);\nconsole.log(xs);"), Some(("javascript", Confidence::High)));
+    assert_eq!(detect("function f(x: number): number {\n    return x;\n}\nconsole.log(f(1));"), Some(("typescript", Confidence::High)));
+    assert_eq!(detect("<?php\necho 'hi';"), Some(("php", Confidence::High)));
+    assert_eq!(detect("using System;\nConsole.WriteLine(1);"), Some(("csharp", Confidence::High)));
+    assert_eq!(detect("[1, 2].each do |x|\n  puts x\nend"), Some(("ruby", Confidence::High)));
+    assert_eq!(detect("main = putStrLn \"hi\""), Some(("haskell", Confidence::High)));
+    assert_eq!(detect("let x = 1 in\nprint_int x"), Some(("ocaml", Confidence::High)));
+    assert_eq!(detect("print(1)").map(|(_, confidence)| confidence), None);
+    assert_eq!(detect("puts 1").map(|(_, confidence)| confidence), Some(Confidence::Low));
+    assert_eq!(detect("hello world"), None);
+}
+
+#[test]
+fn test_lang_suggestions() {
+    use commands::exec::{ get_lang, get_lang_or_detect, suggest_lang };
+
+    let mut lang_manager = LangManager::new();
+    let languages = lang_manager.get_languages().clone();
+    for (_codes, boxed_lang) in languages {
+        lang_manager.set_language_available(boxed_lang.get_lang_name(), true);
+    }
+    assert_eq!(suggest_lang(&lang_manager, "rsut").as_deref(), Some("rust"));
+    assert_eq!(suggest_lang(&lang_manager, "Pyhton").as_deref(), Some("python"));
+    assert_eq!(suggest_lang(&lang_manager, "c++").as_deref(), Some("cpp"));
+    assert_eq!(suggest_lang(&lang_manager, "javascirpt").as_deref(), Some("javascript"));
+    assert_eq!(suggest_lang(&lang_manager, "rust"), None, "Known codes are not typos");
+    assert_eq!(suggest_lang(&lang_manager, "brainfuck"), None);
+
+    match get_lang(&lang_manager, "rsut") {
+        Err(e) => assert!(e.to_string().contains("did you mean `rust`?"), "{}", e),
+        Ok(_) => panic!("`rsut` is not a language"),
+    }
+
+    let code = "fn main() {\n    println!(\"hi\");\n}";
+    let (lang, detection) = get_lang_or_detect(&lang_manager, "", code).unwrap();
+    assert_eq!(lang.get_lang_name(), "Rust");
+    assert!(detection.is_some());
+    let (lang, detection) = get_lang_or_detect(&lang_manager, "py", code).unwrap();
+    assert_eq!(lang.get_lang_name(), "Python", "Tags win over guesses");
+    assert!(detection.is_none());
+    assert_eq!(get_lang_or_detect(&lang_manager, "code", code).unwrap().0.get_lang_name(), "Rust");
+    assert!(get_lang_or_detect(&lang_manager, "rsut", code).is_err(), "Typos are reported");
+    assert!(get_lang_or_detect(&lang_manager, "", "hello world").is_err());
+}
+
+#[test]
+fn test_exec_args() {
+    use commands::exec::get_exec_args;
+
+    assert_eq!(get_exec_args("~exec --arg foo\n```c\n```", "~").as_deref(), Some(" --arg foo\n```c\n```"));
+    assert_eq!(get_exec_args("  ~RUN```py\n```", "~").as_deref(), Some("```py\n```"));
+    assert_eq!(get_exec_args("!code", "!").as_deref(), Some(""));
+    assert_eq!(get_exec_args("~bench --bench 5", "~").as_deref(), Some("--bench 10  --bench 5"));
+    assert_eq!(get_exec_args("~exec", "!"), None);
+    assert_eq!(get_exec_args("~executable", "~"), None);
+    assert_eq!(get_exec_args("~languages", "~"), None);
+    assert_eq!(get_exec_args("look at this: ~exec", "~"), None);
+}
+
+#[test]
+fn test_rerun_delay() {
+    use std::time::Instant;
+    use serenity::model::id::UserId;
+    use commands::exec::{ get_rerun_delay, EXEC_DELAY };
+
+    let mut executions = HashMap::new();
+    assert_eq!(get_rerun_delay(&executions, UserId(1)), None);
+    executions.insert(UserId(1), Instant::now());
+    assert_eq!(get_rerun_delay(&executions, UserId(1)), Some(EXEC_DELAY));
+    assert_eq!(get_rerun_delay(&executions, UserId(2)), None, "Users are limited separately");
+    executions.insert(UserId(1), Instant::now() - Duration::from_millis(EXEC_DELAY * 1000 - 1500));
+    assert_eq!(get_rerun_delay(&executions, UserId(1)), Some(2));
+    executions.insert(UserId(1), Instant::now() - Duration::from_secs(EXEC_DELAY));
+    assert_eq!(get_rerun_delay(&executions, UserId(1)), None);
+}
+
+#[test]
+fn test_find_exec_command() {
+    use std::time::Instant;
+    use serenity::model::id::{ ChannelId, MessageId };
+    use commands::exec::{ find_exec_command, take_expired_replies, ExecReply };
+
+    let mut replies = HashMap::new();
+    replies.insert(MessageId(1), ExecReply { reply_id: MessageId(2), channel_id: ChannelId(9), created: Instant::now(), snippets: vec![1], pending_lang: None });
+    replies.insert(MessageId(3), ExecReply { reply_id: MessageId(4), channel_id: ChannelId(9), created: Instant::now(), snippets: vec![2, 3], pending_lang: None });
+    assert_eq!(find_exec_command(&replies, MessageId(4)), Some(MessageId(3)));
+    assert_eq!(find_exec_command(&replies, MessageId(2)), Some(MessageId(1)));
+    assert_eq!(find_exec_command(&replies, MessageId(1)), None, "Commands are not their own result");
+
+    // Replies are forgotten once the re-run window is over, and handed back to take their reactions back
+    replies.get_mut(&MessageId(1)).unwrap().created = Instant::now() - Duration::from_secs(700);
+    let expired = take_expired_replies(&mut replies, Duration::from_secs(600));
+    assert_eq!(expired.iter().map(|reply| reply.reply_id).collect::<Vec<_>>(), vec![MessageId(2)]);
+    assert_eq!(find_exec_command(&replies, MessageId(2)), None);
+    assert_eq!(find_exec_command(&replies, MessageId(4)), Some(MessageId(3)));
+}
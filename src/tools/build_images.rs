@@ -6,6 +6,19 @@ use crate::lang_manager::LangManager;
 /// 
 /// This command should be used after pulling from the repository.
 pub fn build_images() {
-    let mut lang_manager = LangManager::new();
-    lang_manager.check_available_languages();
+    let lang_manager = LangManager::new();
+    let concurrency = crate::read_settings()
+        .map(|settings| settings.build_concurrency)
+        .unwrap_or_else(|_| crate::default_build_concurrency());
+    let _ = lang_manager.check_languages(&lang_manager.get_language_names(), concurrency).join();
+
+    let mut results: Vec<(String, bool)> = lang_manager
+        .get_languages()
+        .values()
+        .map(|lang| (lang.get_lang_name(), lang_manager.is_language_available(lang)))
+        .collect();
+    results.sort();
+    for (lang_name, available) in results {
+        println!("{}: {}", lang_name, if available { "Available" } else { "Unavailable" });
+    }
 }